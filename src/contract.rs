@@ -1,20 +1,41 @@
 use cosmwasm_std::{
-    entry_point, to_binary, Binary, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Response, Storage,
-    Uint128,
+    entry_point, from_binary, to_binary, Addr, Api, BankMsg, Binary, Coin, CosmosMsg, Decimal,
+    Deps, DepsMut, Env, Event, MessageInfo, Reply, Response, StdResult, Storage, SubMsg,
+    SubMsgResponse, SubMsgResult, Timestamp, Uint128, WasmMsg,
 };
 
 use secret_toolkit::permit::{validate, Permit, RevokedPermits, TokenPermissions};
 
+use secret_toolkit::utils::HandleCallback;
 use secret_toolkit::viewing_key::{ViewingKey, ViewingKeyStore};
 
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, InstantiateMsg, QueryAnswer, QueryMsg, QueryWithPermit};
+use crate::msg::{
+    ContractInfo, ExecuteMsg, InstantiateMsg, QueryAnswer, QueryMsg, QueryWithPermit, ReceiveMsg,
+    RoleAssignment, Snip20Msg, StakeAssignment,
+};
 use crate::state::{
-    Config, ExtActionProposition, COMPLETED_ACTIONS, CONFIG_KEY, PENDING_ACTIONS,
-    PREFIX_REVOKED_PERMITS, STAKEHOLDERS, TOT_PROPS, TOT_VOTES, VOTE_RECORD,
+    Asset, Ballot, Config, Deposit, ExecutionResult, ExtActionProposition, ProposalStatus, Role,
+    Schedule, Spend, SpendAllowance, Tally, Threshold, Transferer, Unbonding, Vote, VoteAllowance,
+    CONFIG_KEY, DELEGATIONS, DELEGATORS, DEPOSITS, EXECUTING_PROP, MEMBER_ROLES, PENDING_PROPS,
+    PENDING_PROPS_BY_PROPOSER, PREFIX_REVOKED_PERMITS, PROPOSALS, ROLES, SCHEDULES,
+    SPEND_ALLOWANCES, STAKEHOLDERS, TOKENS, TOT_DEPOSITS, TOT_PROPS, TOT_SCHEDULES, TOT_VOTES,
+    UNBONDING, VESTING, VOTE_ALLOWANCES, VOTE_RECORD, VOTE_SNAPSHOTS,
 };
 
 pub const DEFAULT_PAGE_SIZE: u32 = 200;
+pub const EXECUTE_PROP_REPLY_ID: u64 = 1;
+pub const PROP_MSG_REPLY_ID: u64 = 2;
+pub const MAX_TITLE_LENGTH: usize = 128;
+pub const MAX_DESCRIPTION_LENGTH: usize = 4096;
+pub const MAX_LINKS: usize = 10;
+pub const MAX_LINK_LENGTH: usize = 512;
+pub const MAX_SPENDS_PER_WINDOW: usize = 100;
+pub const MAX_SPEND_WINDOW: u64 = 365 * 24 * 60 * 60;
+pub const MIN_TIME_LIMIT: u64 = 60;
+pub const MAX_TIME_LIMIT: u64 = 365 * 24 * 60 * 60;
+pub const MAX_EXECUTION_DELAY: u64 = 365 * 24 * 60 * 60;
+pub const MAX_UNBONDING_PERIOD: u64 = 2 * 365 * 24 * 60 * 60;
 
 #[entry_point]
 pub fn instantiate(
@@ -23,23 +44,121 @@ pub fn instantiate(
     info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
-    let config = Config {
-        contract_address: env.contract.address,
-        prop_time_limit: msg.time_limit,
-    };
+    if msg.time_limit < MIN_TIME_LIMIT || msg.time_limit > MAX_TIME_LIMIT {
+        return Err(ContractError::InvalidTimeLimit {
+            min: MIN_TIME_LIMIT,
+            max: MAX_TIME_LIMIT,
+        });
+    }
+    let execution_delay = msg.execution_delay.unwrap_or_default();
+    if execution_delay > MAX_EXECUTION_DELAY {
+        return Err(ContractError::InvalidExecutionDelay {
+            max: MAX_EXECUTION_DELAY,
+        });
+    }
+
+    if let Some(staking) = &msg.staking {
+        // unstaked votes must stay unclaimable until every prop they were counted in closes, which
+        // passed props only do once their execution delay is over
+        let min_unbonding_period = msg.time_limit + execution_delay;
+        // votes have to be backed by staked tokens, and the total moves with every stake
+        if !msg.stakeholders.is_empty() {
+            return Err(ContractError::StakedVotesOnly {});
+        } else if staking.unbonding_period < min_unbonding_period
+            || staking.unbonding_period > MAX_UNBONDING_PERIOD
+        {
+            return Err(ContractError::InvalidUnbondingPeriod {
+                min: min_unbonding_period,
+                max: MAX_UNBONDING_PERIOD,
+            });
+        } else if staking.min_stake.is_zero() {
+            return Err(ContractError::BelowMinimumStake {
+                min: Uint128::from(1_u128),
+            });
+        } else if !matches!(msg.threshold, Threshold::Percentage { .. }) {
+            return Err(ContractError::InvalidThreshold {
+                reason: "a staking token requires a percentage threshold".to_string(),
+            });
+        }
+    }
 
     let mut total_votes = Uint128::from(0_u128);
     for stakeholder in msg.stakeholders.iter() {
-        STAKEHOLDERS.insert(deps.storage, &stakeholder.holder, &stakeholder.stake)?;
+        let holder = validate_stakeholder(deps.api, &stakeholder.holder)?;
+        if stakeholder.stake.is_zero() {
+            return Err(ContractError::ZeroStake { holder });
+        } else if STAKEHOLDERS.contains(deps.storage, &holder) {
+            return Err(ContractError::DuplicateStakeholder { holder });
+        }
+
+        STAKEHOLDERS.insert(deps.storage, &holder, &stakeholder.stake)?;
+        set_vesting(deps.storage, &holder, stakeholder)?;
+        MEMBER_ROLES.insert(deps.storage, &holder, &Role::all())?;
         total_votes += stakeholder.stake;
     }
+    if total_votes.is_zero() && msg.staking.is_none() {
+        return Err(ContractError::NoVotes {});
+    }
     TOT_VOTES.save(deps.storage, &total_votes)?;
+    set_roles(deps.api, deps.storage, &msg.roles.unwrap_or_default())?;
+
+    let members = STAKEHOLDERS.get_len(deps.storage)?;
+    validate_threshold(&msg.threshold, total_votes, members as usize)?;
+
+    let max_execution_attempts = msg.max_execution_attempts.unwrap_or(1);
+    if max_execution_attempts == 0 {
+        return Err(ContractError::InvalidExecutionAttempts {});
+    }
+
+    let mut response = Response::new();
+    if let Some(staking) = &msg.staking {
+        deps.api.addr_validate(&staking.token.address)?;
+        response = response.add_message(
+            Snip20Msg::RegisterReceive {
+                code_hash: env.contract.code_hash.clone(),
+                padding: None,
+            }
+            .to_cosmos_msg(
+                staking.token.code_hash.clone(),
+                staking.token.address.clone(),
+                None,
+            )?,
+        );
+    }
+    if let Some(quorum) = msg.quorum {
+        if quorum > Decimal::one() {
+            return Err(ContractError::InvalidQuorum {});
+        }
+    }
+    let veto_threshold = msg
+        .veto_threshold
+        .unwrap_or_else(|| Decimal::from_ratio(1_u128, 3_u128));
+    if veto_threshold.is_zero() || veto_threshold > Decimal::one() {
+        return Err(ContractError::InvalidVetoThreshold {});
+    }
+
+    let config = Config {
+        contract_address: env.contract.address,
+        prop_time_limit: msg.time_limit,
+        threshold: msg.threshold,
+        quorum: msg.quorum,
+        veto_threshold,
+        restrict_execution: msg.restrict_execution,
+        joining_roles: msg.joining_roles.unwrap_or_else(Role::all),
+        lock_votes: msg.lock_votes,
+        execution_delay,
+        max_execution_attempts,
+        proposal_limits: msg.proposal_limits.unwrap_or_default(),
+        staking: msg.staking,
+    };
 
     // Save data to storage
     CONFIG_KEY.save(deps.storage, &config)?;
     TOT_PROPS.save(deps.storage, &Uint128::from(0_u128))?;
+    TOT_SCHEDULES.save(deps.storage, &Uint128::from(0_u128))?;
+    TOT_DEPOSITS.save(deps.storage, &Uint128::from(0_u128))?;
 
-    Ok(Response::new())
+    Ok(response)
 }
 
 //-------------------------------------------- HANDLES ---------------------------------
@@ -57,9 +176,90 @@ pub fn execute(
         ExecuteMsg::TransferVotes {
             recipient,
             num_votes,
-        } => transfer_votes(deps, env, info, recipient, num_votes),
-        ExecuteMsg::ProposeAction { prop_msg } => propose_new_action(deps, env, info, prop_msg),
-        ExecuteMsg::VoteAction { action_prop } => vote_new_action(deps, env, info, action_prop),
+        } => transfer_votes(
+            deps,
+            env,
+            info,
+            vec![Transferer {
+                addr: recipient,
+                amount: num_votes,
+            }],
+        ),
+        ExecuteMsg::TransferVotesBatch { transfers } => transfer_votes(deps, env, info, transfers),
+        ExecuteMsg::IncreaseVoteAllowance {
+            spender,
+            amount,
+            expiration,
+        } => change_vote_allowance(deps, env, info, spender, amount, true, expiration),
+        ExecuteMsg::DecreaseVoteAllowance {
+            spender,
+            amount,
+            expiration,
+        } => change_vote_allowance(deps, env, info, spender, amount, false, expiration),
+        ExecuteMsg::TransferVotesFrom {
+            owner,
+            recipient,
+            num_votes,
+        } => transfer_votes_from(deps, env, info, owner, recipient, num_votes),
+        ExecuteMsg::Receive {
+            sender,
+            from,
+            amount,
+            memo,
+            msg,
+        } => receive(deps, env, info, sender, from, amount, memo, msg),
+        ExecuteMsg::Unstake { amount } => unstake(deps, env, info, amount),
+        ExecuteMsg::ClaimUnbonded {} => claim_unbonded(deps, env, info),
+        ExecuteMsg::ProposeAction {
+            title,
+            description,
+            links,
+            prop_msgs,
+        } => propose_new_action(
+            deps,
+            env,
+            info,
+            title,
+            description,
+            links.unwrap_or_default(),
+            prop_msgs,
+        ),
+        ExecuteMsg::VoteAction { action_prop, vote } => {
+            vote_new_action(deps, env, info, action_prop, Some(vote))
+        }
+        ExecuteMsg::RetractVote { action_prop } => {
+            vote_new_action(deps, env, info, action_prop, None)
+        }
+        ExecuteMsg::DelegateVotes { delegate } => delegate_votes(deps, info, Some(delegate)),
+        ExecuteMsg::Undelegate {} => delegate_votes(deps, info, None),
+        ExecuteMsg::Execute { proposal_id } => execute_action(deps, env, info, proposal_id),
+        ExecuteMsg::RunProposal { proposal_id } => run_action(deps, env, info, proposal_id),
+        ExecuteMsg::SetRoles {
+            assignments,
+            joining_roles,
+        } => update_roles(deps, env, info, assignments, joining_roles),
+        ExecuteMsg::CreateSchedule {
+            msg,
+            start,
+            interval,
+            runs,
+        } => create_schedule(deps, env, info, msg, start, interval, runs),
+        ExecuteMsg::CancelSchedule { id } => cancel_schedule(deps, env, info, id),
+        ExecuteMsg::ExecuteSchedule { id } => execute_schedule(deps, env, id),
+        ExecuteMsg::SetSpendAllowance {
+            member,
+            asset,
+            limit,
+            window,
+        } => set_spend_allowance(deps, env, info, member, asset, limit, window),
+        ExecuteMsg::Spend {
+            asset,
+            recipient,
+            amount,
+        } => spend(deps, env, info, asset, recipient, amount),
+        ExecuteMsg::RegisterToken { token } => register_token(deps, env, info, token),
+        ExecuteMsg::CancelProposal { id } => cancel_action(deps, env, info, id),
+        ExecuteMsg::UpdateMembers { add, remove } => update_members(deps, env, info, add, remove),
         ExecuteMsg::RevokePermit { permit_name } => revoke_permit(deps, env, info, permit_name),
         ExecuteMsg::PurgeExpiredActions {
             start_page,
@@ -70,474 +270,3419 @@ pub fn execute(
 
 /// Returns Result<Response, ContractError>
 ///
-/// moves votes from one address to another
+/// moves votes from the sender to one or more recipients
 ///
 /// # Arguments
 ///
-/// * `deps`    - DepsMut containing all the contract's external dependencies
-/// * `env`     - Env of contract's environment
-/// * `info`    - Carries the info of who sent the message and how much native funds were sent along
+/// * `deps`      - DepsMut containing all the contract's external dependencies
+/// * `env`       - Env of contract's environment
+/// * `info`      - Carries the info of who sent the message and how much native funds were sent along
+/// * `transfers` - recipients and the votes each of them receives
 fn transfer_votes(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    recipient: String,
-    num_votes: Uint128,
+    transfers: Vec<Transferer>,
 ) -> Result<Response, ContractError> {
     if !STAKEHOLDERS.contains(deps.storage, &info.sender.to_string()) {
         return Err(ContractError::CustomError {
             val: "You do not have a share in this contract".to_string(),
         });
-    }
-
-    let sender_votes = STAKEHOLDERS
-        .get(deps.storage, &info.sender.to_string())
-        .unwrap();
-    if num_votes > sender_votes {
+    } else if transfers.is_empty() {
         return Err(ContractError::CustomError {
-            val: "You cannot transfer a larger share than you posess".to_string(),
+            val: "A transfer needs at least one recipient".to_string(),
         });
-    } else {
-        STAKEHOLDERS.insert(
-            deps.storage,
-            &info.sender.to_string(),
-            &(sender_votes - num_votes),
-        )?;
     }
 
-    if STAKEHOLDERS.contains(deps.storage, &recipient) {
-        let reciever_votes = STAKEHOLDERS.get(deps.storage, &recipient).unwrap();
-        STAKEHOLDERS.insert(
+    let mut events = vec![];
+    for transfer in transfers.iter() {
+        let recipient = deps.api.addr_validate(&transfer.addr)?;
+        move_votes(
             deps.storage,
-            &info.sender.to_string(),
-            &(reciever_votes + num_votes),
+            info.sender.as_str(),
+            recipient.as_str(),
+            transfer.amount,
+            env.block.time,
         )?;
-    } else {
-        STAKEHOLDERS.insert(deps.storage, &info.sender.to_string(), &num_votes)?;
+
+        events.push(
+            Event::new("transfer_votes")
+                .add_attribute("from", info.sender.as_str())
+                .add_attribute("to", recipient.as_str())
+                .add_attribute("amount", transfer.amount.to_string()),
+        );
     }
 
-    Ok(Response::new())
+    Ok(Response::new().add_events(events))
 }
 
 /// Returns Result<Response, ContractError>
 ///
-/// adds a votable prop to place action
+/// raises or lowers the votes a spender can transfer on the sender's behalf
 ///
 /// # Arguments
 ///
-/// * `deps`    - DepsMut containing all the contract's external dependencies
-/// * `env`     - Env of contract's environment
-/// * `info`    - Carries the info of who sent the message and how much native funds were sent along
-fn propose_new_action(
+/// * `deps`       - DepsMut containing all the contract's external dependencies
+/// * `env`        - Env of contract's environment
+/// * `info`       - Carries the info of who sent the message and how much native funds were sent along
+/// * `spender`    - address the allowance is given to
+/// * `amount`     - votes added to or taken from the allowance
+/// * `increase`   - true to raise the allowance, false to lower it
+/// * `expiration` - optional time in seconds after which the allowance can no longer be used
+fn change_vote_allowance(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    prop_msg: CosmosMsg,
+    spender: String,
+    amount: Uint128,
+    increase: bool,
+    expiration: Option<u64>,
 ) -> Result<Response, ContractError> {
-    if !STAKEHOLDERS.contains(deps.storage, &info.sender.to_string()) {
+    let spender = deps.api.addr_validate(&spender)?;
+    if spender == info.sender {
         return Err(ContractError::CustomError {
-            val: "You do not have a share in this contract".to_string(),
+            val: "You cannot give yourself an allowance".to_string(),
         });
     }
-    let new_prop = ExtActionProposition {
-        confirmed_votes: Uint128::from(0_u128),
-        proposed_at: env.block.time,
-        cosmos_msg: prop_msg,
+
+    let allowances = VOTE_ALLOWANCES.add_suffix(info.sender.as_bytes());
+    let mut allowance = allowances
+        .get(deps.storage, &spender.to_string())
+        .unwrap_or_default();
+
+    // an expired allowance starts over from zero
+    if allowance.is_expired(env.block.time) {
+        allowance = VoteAllowance::default();
+    }
+    allowance.amount = if increase {
+        allowance.amount.saturating_add(amount)
+    } else {
+        allowance.amount.saturating_sub(amount)
     };
+    if expiration.is_some() {
+        allowance.expiration = expiration;
+    }
 
-    let prop_num = TOT_PROPS.load(deps.storage)?;
-    TOT_PROPS.save(deps.storage, &(prop_num + Uint128::from(1_u128)))?;
-    PENDING_ACTIONS.insert(deps.storage, &(prop_num + Uint128::from(1_u128)), &new_prop)?;
+    if allowance.amount.is_zero() {
+        allowances.remove(deps.storage, &spender.to_string())?;
+    } else {
+        allowances.insert(deps.storage, &spender.to_string(), &allowance)?;
+    }
 
-    Ok(Response::new())
+    Ok(Response::new()
+        .add_attribute("spender", spender.as_str())
+        .add_attribute("allowance", allowance.amount.to_string()))
 }
 
 /// Returns Result<Response, ContractError>
 ///
-/// votes in favor of new action
+/// moves votes from a stakeholder to a recipient, using the allowance the stakeholder gave the
+/// sender
 ///
 /// # Arguments
 ///
-/// * `deps`    - DepsMut containing all the contract's external dependencies
-/// * `env`     - Env of contract's environment
-/// * `info`    - Carries the info of who sent the message and how much native funds were sent along
-fn vote_new_action(
+/// * `deps`      - DepsMut containing all the contract's external dependencies
+/// * `env`       - Env of contract's environment
+/// * `info`      - Carries the info of who sent the message and how much native funds were sent along
+/// * `owner`     - stakeholder the votes are taken from
+/// * `recipient` - address the votes are given to
+/// * `num_votes` - number of votes being moved
+fn transfer_votes_from(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    action_prop: Uint128,
+    owner: String,
+    recipient: String,
+    num_votes: Uint128,
 ) -> Result<Response, ContractError> {
-    if !PENDING_ACTIONS.contains(deps.storage, &action_prop) {
-        return Err(ContractError::CustomError {
-            val: "This propostion does not exist".to_string(),
-        });
-    } else if !STAKEHOLDERS.contains(deps.storage, &info.sender.to_string()) {
-        return Err(ContractError::CustomError {
-            val: "You do not have a share in this contract".to_string(),
-        });
-    } else if VOTE_RECORD
-        .add_suffix(&action_prop.to_be_bytes())
-        .contains(deps.storage, &info.sender.to_string())
-    {
-        return Err(ContractError::CustomError {
-            val: "You have already voted on this prop".to_string(),
+    let owner = deps.api.addr_validate(&owner)?;
+    let recipient = deps.api.addr_validate(&recipient)?;
+
+    let allowances = VOTE_ALLOWANCES.add_suffix(owner.as_bytes());
+    let allowance = allowances
+        .get(deps.storage, &info.sender.to_string())
+        .filter(|allowance| !allowance.is_expired(env.block.time))
+        .unwrap_or_default();
+    if allowance.amount < num_votes {
+        return Err(ContractError::InsufficientAllowance {
+            allowance: allowance.amount,
+            required: num_votes,
         });
     }
 
-    // records voting roll
-    VOTE_RECORD.add_suffix(&action_prop.to_be_bytes()).insert(
+    move_votes(
         deps.storage,
-        &info.sender.to_string(),
-        &true,
+        owner.as_str(),
+        recipient.as_str(),
+        num_votes,
+        env.block.time,
     )?;
 
-    let mut prop = PENDING_ACTIONS.get(deps.storage, &action_prop).unwrap();
-    let tot_votes = TOT_VOTES.load(deps.storage)?;
-
-    // Check if expiration time has passed
-    let config = CONFIG_KEY.load(deps.storage)?;
-    if prop.proposed_at.plus_seconds(config.prop_time_limit) > env.block.time {
-        PENDING_ACTIONS.remove(deps.storage, &action_prop)?;
-        return Ok(Response::new().add_attribute("Removed Prop", "Timed Out"));
-    }
-
-    if prop.confirmed_votes.u128() < (tot_votes.u128() / 2) {
-        let votes = STAKEHOLDERS
-            .get(deps.storage, &info.sender.to_string())
-            .unwrap();
-        prop.confirmed_votes += votes;
-        PENDING_ACTIONS.insert(deps.storage, &action_prop, &prop)?;
+    let remaining = allowance.amount - num_votes;
+    if remaining.is_zero() {
+        allowances.remove(deps.storage, &info.sender.to_string())?;
     } else {
-        COMPLETED_ACTIONS.insert(deps.storage, &action_prop, &prop)?;
-        PENDING_ACTIONS.remove(deps.storage, &action_prop)?;
-        return Ok(Response::new().add_message(prop.cosmos_msg));
+        allowances.insert(
+            deps.storage,
+            &info.sender.to_string(),
+            &VoteAllowance {
+                amount: remaining,
+                expiration: allowance.expiration,
+            },
+        )?;
     }
 
-    Ok(Response::new())
+    Ok(Response::new().add_event(
+        Event::new("transfer_votes")
+            .add_attribute("from", owner.as_str())
+            .add_attribute("to", recipient.as_str())
+            .add_attribute("amount", num_votes.to_string())
+            .add_attribute("spender", info.sender.as_str()),
+    ))
 }
 
 /// Returns Result<Response, ContractError>
 ///
-/// votes in favor of new action
+/// handles tokens sent to the contract through the SNIP-20 `Send` hook, staking them if asked to
+/// and otherwise logging them as a deposit into the treasury
 ///
 /// # Arguments
 ///
-/// * `deps`    - DepsMut containing all the contract's external dependencies
-/// * `env`     - Env of contract's environment
-/// * `info`    - Carries the info of who sent the message and how much native funds were sent along
-fn purge_expired_actions(
+/// * `deps`   - DepsMut containing all the contract's external dependencies
+/// * `env`    - Env of contract's environment
+/// * `info`   - Carries the info of who sent the message and how much native funds were sent along
+/// * `sender` - address that called the token's Send
+/// * `from`   - owner of the tokens that were sent
+/// * `amount` - number of tokens that were sent
+/// * `memo`   - optional memo sent along with the tokens
+/// * `msg`    - optional ReceiveMsg sent along with the tokens
+#[allow(clippy::too_many_arguments)]
+fn receive(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    start_page: Option<u32>,
-    page_size: Option<u32>,
+    sender: Addr,
+    from: Addr,
+    amount: Uint128,
+    memo: Option<String>,
+    msg: Option<Binary>,
 ) -> Result<Response, ContractError> {
-    if !STAKEHOLDERS.contains(deps.storage, &info.sender.to_string()) {
-        return Err(ContractError::CustomError {
-            val: "You do not have a share in this contract".to_string(),
-        });
-    }
     let config = CONFIG_KEY.load(deps.storage)?;
+    let msg: Option<ReceiveMsg> = match msg {
+        Some(msg) => Some(from_binary(&msg)?),
+        None => None,
+    };
 
-    // Check for defaults
-    let start = start_page.unwrap_or(0);
-    let size = page_size.unwrap_or(DEFAULT_PAGE_SIZE);
-
-    let paginated_action_iter = PENDING_ACTIONS.paging(deps.storage, start, size)?;
-
-    //let init_len = PENDING_ACTIONS.get_len(deps.storage)?;
+    match msg {
+        None => {
+            if !TOKENS.contains(deps.storage, &info.sender.to_string()) {
+                return Err(ContractError::CustomError {
+                    val: "This token is not registered with the treasury".to_string(),
+                });
+            }
 
-    // Loop through Issuers and cnvert to ExportIssuer
-    for action in paginated_action_iter {
-        // Check if expiration time has passed
-        if action.1.proposed_at.plus_seconds(config.prop_time_limit) > env.block.time {
-            PENDING_ACTIONS.remove(deps.storage, &action.0)?;
+            let id = TOT_DEPOSITS.load(deps.storage)? + Uint128::from(1_u128);
+            TOT_DEPOSITS.save(deps.storage, &id)?;
+            DEPOSITS.insert(
+                deps.storage,
+                &id,
+                &Deposit {
+                    token: info.sender.clone(),
+                    sender,
+                    from: from.clone(),
+                    amount,
+                    memo,
+                    received_at: env.block.time,
+                },
+            )?;
+
+            Ok(Response::new().add_event(
+                Event::new("deposit")
+                    .add_attribute("deposit_id", id.to_string())
+                    .add_attribute("token", info.sender.as_str())
+                    .add_attribute("from", from.as_str())
+                    .add_attribute("amount", amount.to_string()),
+            ))
+        }
+        Some(ReceiveMsg::Stake {}) => {
+            match &config.staking {
+                Some(staking) if staking.token.address == info.sender.as_str() => {}
+                Some(_) => return Err(ContractError::Unauthorized {}),
+                None => return Err(ContractError::NoStakingToken {}),
+            }
+            stake(deps, from, amount)
         }
     }
+}
 
-    //let removed_count = init_len - PENDING_ACTIONS.get_len(deps.storage)?;
+/// Returns Result<Response, ContractError>
+///
+/// gives votes for staked tokens, making the staker a stakeholder if they were not one
+///
+/// # Arguments
+///
+/// * `deps`   - DepsMut containing all the contract's external dependencies
+/// * `staker` - address the votes are given to
+/// * `amount` - number of tokens staked
+fn stake(deps: DepsMut, staker: Addr, amount: Uint128) -> Result<Response, ContractError> {
+    let votes = STAKEHOLDERS
+        .get(deps.storage, &staker.to_string())
+        .unwrap_or_default();
+    check_min_stake(deps.storage, votes + amount)?;
+    if votes.is_zero() {
+        grant_joining_roles(deps.storage, staker.as_str())?;
+    }
+    STAKEHOLDERS.insert(deps.storage, &staker.to_string(), &(votes + amount))?;
 
-    Ok(Response::new())
+    let total_votes = TOT_VOTES.load(deps.storage)? + amount;
+    TOT_VOTES.save(deps.storage, &total_votes)?;
+
+    Ok(Response::new().add_event(
+        Event::new("stake")
+            .add_attribute("staker", staker.as_str())
+            .add_attribute("amount", amount.to_string()),
+    ))
 }
 
 /// Returns Result<Response, ContractError>
 ///
-/// create a viewing key
+/// gives up votes for the staking token, which can be claimed once the unbonding period is over.
+/// props made before unstaking are still tallied with the votes the staker had when they were made
 ///
 /// # Arguments
 ///
-/// * `deps`    - DepsMut containing all the contract's external dependencies
-/// * `env`     - Env of contract's environment
-/// * `info`    - Carries the info of who sent the message and how much native funds were sent along
-/// * `entropy` - string to be used as an entropy source for randomization
-fn try_create_key(
+/// * `deps`   - DepsMut containing all the contract's external dependencies
+/// * `env`    - Env of contract's environment
+/// * `info`   - Carries the info of who sent the message and how much native funds were sent along
+/// * `amount` - number of tokens unstaked
+fn unstake(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    entropy: String,
+    amount: Uint128,
 ) -> Result<Response, ContractError> {
-    let key = ViewingKey::create(
-        deps.storage,
-        &info,
-        &env,
-        info.sender.as_str(),
-        entropy.as_bytes(),
-    );
+    let staking = match CONFIG_KEY.load(deps.storage)?.staking {
+        Some(staking) => staking,
+        None => return Err(ContractError::NoStakingToken {}),
+    };
 
-    Ok(Response::new().add_attribute("viewing_key", key))
+    let staker = info.sender.to_string();
+    let votes = STAKEHOLDERS.get(deps.storage, &staker).unwrap_or_default();
+    if amount.is_zero() || amount > votes {
+        return Err(ContractError::CustomError {
+            val: format!("You can unstake between 1 and {} tokens", votes),
+        });
+    } else if amount == votes {
+        remove_stakeholder(deps.storage, &staker)?;
+    } else {
+        check_min_stake(deps.storage, votes - amount)?;
+        STAKEHOLDERS.insert(deps.storage, &staker, &(votes - amount))?;
+    }
+
+    let total_votes = TOT_VOTES.load(deps.storage)? - amount;
+    TOT_VOTES.save(deps.storage, &total_votes)?;
+
+    let release_at = env.block.time.seconds() + staking.unbonding_period;
+    let mut unbonding = UNBONDING.get(deps.storage, &staker).unwrap_or_default();
+    unbonding.push(Unbonding { amount, release_at });
+    UNBONDING.insert(deps.storage, &staker, &unbonding)?;
+
+    Ok(Response::new()
+        .add_attribute("unstaked", amount.to_string())
+        .add_attribute("release_at", release_at.to_string()))
 }
 
 /// Returns Result<Response, ContractError>
 ///
-/// sets the viewing key
+/// sends the sender the unstaked tokens whose unbonding period is over
 ///
 /// # Arguments
 ///
 /// * `deps` - DepsMut containing all the contract's external dependencies
+/// * `env`  - Env of contract's environment
 /// * `info` - Carries the info of who sent the message and how much native funds were sent along
-/// * `key`  - string slice to be used as the viewing key
-fn try_set_key(deps: DepsMut, info: MessageInfo, key: &str) -> Result<Response, ContractError> {
-    ViewingKey::set(deps.storage, info.sender.as_str(), key);
+fn claim_unbonded(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    let staking = match CONFIG_KEY.load(deps.storage)?.staking {
+        Some(staking) => staking,
+        None => return Err(ContractError::NoStakingToken {}),
+    };
 
-    Ok(Response::new().add_attribute("viewing_key", key))
+    let staker = info.sender.to_string();
+    let (released, unbonding): (Vec<Unbonding>, Vec<Unbonding>) = UNBONDING
+        .get(deps.storage, &staker)
+        .unwrap_or_default()
+        .into_iter()
+        .partition(|unbonding| unbonding.release_at <= env.block.time.seconds());
+
+    let amount = released
+        .iter()
+        .fold(Uint128::zero(), |total, unbonding| total + unbonding.amount);
+    if amount.is_zero() {
+        return Err(ContractError::CustomError {
+            val: "You do not have any tokens to claim yet".to_string(),
+        });
+    }
+
+    if unbonding.is_empty() {
+        UNBONDING.remove(deps.storage, &staker)?;
+    } else {
+        UNBONDING.insert(deps.storage, &staker, &unbonding)?;
+    }
+
+    let transfer = Snip20Msg::Transfer {
+        recipient: staker,
+        amount,
+        memo: None,
+        padding: None,
+    }
+    .to_cosmos_msg(staking.token.code_hash, staking.token.address, None)?;
+
+    Ok(Response::new()
+        .add_message(transfer)
+        .add_attribute("claimed", amount.to_string()))
 }
 
-fn revoke_permit(
+/// Returns Result<Response, ContractError>
+///
+/// adds a votable prop to place action
+///
+/// # Arguments
+///
+/// * `deps`        - DepsMut containing all the contract's external dependencies
+/// * `env`         - Env of contract's environment
+/// * `info`        - Carries the info of who sent the message and how much native funds were sent along
+/// * `title`       - short name of the action
+/// * `description` - what the action does and why
+/// * `links`       - external references for reviewers
+/// * `prop_msgs`   - messages to dispatch, in order, if the action passes
+fn propose_new_action(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    permit_name: String,
+    title: String,
+    description: String,
+    links: Vec<String>,
+    prop_msgs: Vec<CosmosMsg>,
 ) -> Result<Response, ContractError> {
-    RevokedPermits::revoke_permit(
+    require_role(deps.storage, info.sender.as_str(), Role::Proposer)?;
+    if prop_msgs.is_empty() {
+        return Err(ContractError::CustomError {
+            val: "A propostion needs at least one message".to_string(),
+        });
+    }
+
+    let config = CONFIG_KEY.load(deps.storage)?;
+    let limits = &config.proposal_limits;
+    let proposer_votes = STAKEHOLDERS
+        .get(deps.storage, &info.sender.to_string())
+        .unwrap_or_default();
+    if vested_votes(
         deps.storage,
-        PREFIX_REVOKED_PERMITS,
-        info.sender.as_ref(),
-        &permit_name,
-    );
+        info.sender.as_str(),
+        proposer_votes,
+        env.block.time,
+    ) < limits.min_votes
+    {
+        return Err(ContractError::InsufficientVotesToPropose {
+            min: limits.min_votes,
+        });
+    }
+    check_pending_caps(deps.storage, &config, &env, info.sender.as_str())?;
 
-    Ok(Response::new())
-}
+    validate_length("title", &title, 1, MAX_TITLE_LENGTH)?;
+    validate_length("description", &description, 0, MAX_DESCRIPTION_LENGTH)?;
+    if links.len() > MAX_LINKS {
+        return Err(ContractError::TooManyLinks { max: MAX_LINKS });
+    }
+    for link in links.iter() {
+        validate_length("link", link, 1, MAX_LINK_LENGTH)?;
+    }
 
-// ---------------------------------------- QUERIES --------------------------------------
+    let prop_num = TOT_PROPS.load(deps.storage)? + Uint128::from(1_u128);
+    TOT_PROPS.save(deps.storage, &prop_num)?;
 
-#[entry_point]
-pub fn query(deps: Deps, msg: QueryMsg) -> Result<Binary, ContractError> {
-    match msg {
-        QueryMsg::WithPermit { permit, query } => permit_queries(deps, permit, query),
-        _ => viewing_keys_queries(deps, msg),
+    // freezes the vested voting weights the prop will be tallied with
+    let stakeholders = STAKEHOLDERS
+        .iter(deps.storage)?
+        .collect::<StdResult<Vec<(String, Uint128)>>>()?;
+    let snapshot = VOTE_SNAPSHOTS.add_suffix(&prop_num.to_be_bytes());
+    let mut total_votes = Uint128::zero();
+    for (holder, votes) in stakeholders.iter() {
+        let vested = vested_votes(deps.storage, holder, *votes, env.block.time);
+        if !vested.is_zero() {
+            snapshot.insert(deps.storage, holder, &vested)?;
+            total_votes += vested;
+        }
     }
+
+    let new_prop = ExtActionProposition {
+        title,
+        description,
+        links,
+        proposer: info.sender,
+        status: ProposalStatus::Open,
+        total_votes,
+        tally: Tally::default(),
+        confirmed_signers: 0,
+        proposed_at: env.block.time,
+        proposed_at_height: env.block.height,
+        cosmos_msgs: prop_msgs,
+        executable_after: None,
+        execution_attempts: 0,
+        execution_result: None,
+    };
+
+    PROPOSALS.insert(deps.storage, &prop_num, &new_prop)?;
+    track_pending_prop(deps.storage, &prop_num, new_prop.proposer.as_str(), true)?;
+
+    Ok(Response::new().add_attribute("proposal_id", prop_num.to_string()))
 }
 
-/// Returns QueryResult from validating a permit and then using its creator's address when
-/// performing the specified query
+/// Returns Result<Response, ContractError>
+///
+/// casts, changes or retracts a ballot on an action that has not closed yet, with the sender's own
+/// votes and those of the stakeholders delegating to them who have not voted themselves
 ///
 /// # Arguments
 ///
-/// * `deps` - a reference to Extern containing all the contract's external dependencies
-/// * `permit` - the permit used to authentic the query
-/// * `query` - the query to perform
-fn permit_queries(
-    deps: Deps,
-    permit: Permit,
-    query: QueryWithPermit,
-) -> Result<Binary, ContractError> {
-    // Validate permit content
+/// * `deps`        - DepsMut containing all the contract's external dependencies
+/// * `env`         - Env of contract's environment
+/// * `info`        - Carries the info of who sent the message and how much native funds were sent along
+/// * `action_prop` - ID of the action being voted on
+/// * `vote`        - ballot option chosen by the voter, None to retract their ballot
+fn vote_new_action(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    action_prop: Uint128,
+    vote: Option<Vote>,
+) -> Result<Response, ContractError> {
+    require_role(deps.storage, info.sender.as_str(), Role::Voter)?;
+    let mut prop = match PROPOSALS.get(deps.storage, &action_prop) {
+        Some(prop) => prop,
+        None => {
+            return Err(ContractError::CustomError {
+                val: "This propostion does not exist".to_string(),
+            })
+        }
+    };
+
+    // rejections only become final when ballots cannot change or the prop was vetoed
     let config = CONFIG_KEY.load(deps.storage)?;
+    let is_votable = match prop.status {
+        ProposalStatus::Open | ProposalStatus::Passed => true,
+        ProposalStatus::Rejected => !config.lock_votes && !is_vetoed(&config, &prop),
+        _ => false,
+    };
+    if !is_votable {
+        return Err(ContractError::CustomError {
+            val: format!("This propostion is {} and cannot be voted on", prop.status),
+        });
+    }
 
-    let viewer = validate(
-        deps,
-        PREFIX_REVOKED_PERMITS,
-        &permit,
-        config.contract_address.to_string(),
-        None,
-    )?;
+    let snapshot = VOTE_SNAPSHOTS.add_suffix(&action_prop.to_be_bytes());
+    // delegating cannot give a vote to a stakeholder the group took it from
+    let mut delegators = DELEGATORS
+        .add_suffix(info.sender.as_bytes())
+        .iter_keys(deps.storage)?
+        .collect::<StdResult<Vec<String>>>()?;
+    delegators.retain(|delegator| roles_of(deps.storage, delegator).contains(&Role::Voter));
+    let own_votes = snapshot.get(deps.storage, &info.sender.to_string());
+    let votes_for_delegators = delegators
+        .iter()
+        .any(|delegator| snapshot.contains(deps.storage, delegator));
+    if own_votes.is_none() && !votes_for_delegators {
+        return Err(ContractError::CustomError {
+            val: "You did not have a share in this contract when this prop was made".to_string(),
+        });
+    }
 
-    // Permit validated! We can now execute the query.
-    match query {
-        QueryWithPermit::AllActions {
-            start_page,
-            page_size,
-        } => {
-            if !permit.check_permission(&TokenPermissions::Balance) {
-                return Err(ContractError::Unauthorized {});
-            }
+    // Check if expiration time has passed
+    if is_expired(&config, &prop, &env) {
+        set_status(
+            deps.storage,
+            &action_prop,
+            &mut prop,
+            ProposalStatus::Expired,
+        )?;
+        PROPOSALS.insert(deps.storage, &action_prop, &prop)?;
+        return Ok(Response::new().add_attribute("status", prop.status.to_string()));
+    }
 
-            query_all_actions(deps, start_page, page_size, viewer)
+    let mut changed = false;
+    if let Some(votes) = own_votes {
+        changed |= apply_ballot(
+            deps.storage,
+            &config,
+            &mut prop,
+            action_prop,
+            info.sender.as_str(),
+            votes,
+            &info.sender,
+            vote,
+            !votes_for_delegators,
+        )?;
+    }
+    for delegator in delegators.iter() {
+        if let Some(votes) = snapshot.get(deps.storage, delegator) {
+            changed |= apply_ballot(
+                deps.storage,
+                &config,
+                &mut prop,
+                action_prop,
+                delegator,
+                votes,
+                &info.sender,
+                vote,
+                false,
+            )?;
         }
-        QueryWithPermit::QueryAction { id } => {
-            if !permit.check_permission(&TokenPermissions::Balance) {
-                return Err(ContractError::Unauthorized {});
-            }
+    }
 
-            query_action(deps, id, viewer)
-        }
-        QueryWithPermit::AllCompletedActions {
-            start_page,
-            page_size,
-        } => query_all_completed_actions(deps, start_page, page_size, viewer),
-        QueryWithPermit::QueryCompletedAction { id } => query_completed_action(deps, id, viewer),
+    if !changed {
+        return Err(ContractError::CustomError {
+            val: "No ballots could be changed on this prop".to_string(),
+        });
+    }
+
+    let status = tally_status(deps.storage, &config, &prop, action_prop)?;
+    if status.is_pending() && !prop.status.is_pending() {
+        check_pending_caps(deps.storage, &config, &env, prop.proposer.as_str())?;
     }
+    // the timelock starts over whenever the prop passes again
+    if status != prop.status {
+        prop.executable_after = match status {
+            ProposalStatus::Passed => Some(env.block.time.plus_seconds(config.execution_delay)),
+            _ => None,
+        };
+    }
+    set_status(deps.storage, &action_prop, &mut prop, status)?;
+    PROPOSALS.insert(deps.storage, &action_prop, &prop)?;
+
+    Ok(Response::new().add_attribute("status", prop.status.to_string()))
 }
 
-pub fn viewing_keys_queries(deps: Deps, msg: QueryMsg) -> Result<Binary, ContractError> {
-    let (address, key) = msg.get_validation_params();
+/// Returns Result<Response, ContractError>
+///
+/// executes a passed action by calling back into the contract as a submessage, so that its
+/// messages revert together if any of them fails and the outcome is recorded by `reply`
+///
+/// # Arguments
+///
+/// * `deps`        - DepsMut containing all the contract's external dependencies
+/// * `env`         - Env of contract's environment
+/// * `info`        - Carries the info of who sent the message and how much native funds were sent along
+/// * `proposal_id` - ID of the action being executed
+fn execute_action(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    proposal_id: Uint128,
+) -> Result<Response, ContractError> {
+    // an action executing another would overwrite the executing action before its reply
+    if info.sender == env.contract.address {
+        return Err(ContractError::Unauthorized {});
+    }
 
-    if !is_key_valid(deps.storage, &address, key) {
-        Err(ContractError::Unauthorized {})
-    } else {
-        match msg {
-            // Base
-            QueryMsg::AllActions {
-                viewer,
-                key: _,
-                start_page,
-                page_size,
-            } => query_all_actions(deps, start_page, page_size, viewer),
-            QueryMsg::QueryAction { viewer, key: _, id } => query_action(deps, id, viewer),
-            QueryMsg::AllCompletedActions {
-                viewer,
-                key: _,
-                start_page,
-                page_size,
-            } => query_all_completed_actions(deps, start_page, page_size, viewer),
-            QueryMsg::QueryCompletedAction { viewer, key: _, id } => {
-                query_completed_action(deps, id, viewer)
-            }
+    let config = CONFIG_KEY.load(deps.storage)?;
+    if config.restrict_execution {
+        require_role(deps.storage, info.sender.as_str(), Role::Executor)?;
+    }
 
-            _ => panic!("This query type does not require authentication"),
+    let mut prop = match PROPOSALS.get(deps.storage, &proposal_id) {
+        Some(prop) => prop,
+        None => {
+            return Err(ContractError::CustomError {
+                val: "This propostion does not exist".to_string(),
+            })
         }
-    }
-}
+    };
 
-fn query_all_actions(
-    deps: Deps,
-    start_page: Option<u32>,
-    page_size: Option<u32>,
-    viewer: String,
-) -> Result<Binary, ContractError> {
-    if !STAKEHOLDERS.contains(deps.storage, &viewer) {
+    if prop.status != ProposalStatus::Passed {
         return Err(ContractError::CustomError {
-            val: "You do not have a share in this contract".to_string(),
+            val: format!("This propostion is {} and cannot be executed", prop.status),
         });
     }
 
-    // Check for defaults
-    let start = start_page.unwrap_or(0);
-    let size = page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+    if is_expired(&config, &prop, &env) {
+        set_status(
+            deps.storage,
+            &proposal_id,
+            &mut prop,
+            ProposalStatus::Expired,
+        )?;
+        PROPOSALS.insert(deps.storage, &proposal_id, &prop)?;
+        return Ok(Response::new().add_attribute("status", prop.status.to_string()));
+    }
 
-    // Prep empty List of Listing Data for response
-    let mut action_list: Vec<(Uint128, ExtActionProposition)> = vec![];
+    if let Some(executable_after) = prop.executable_after {
+        if env.block.time < executable_after {
+            return Err(ContractError::Timelocked { executable_after });
+        }
+    }
 
-    let mut paginated_action_iter = PENDING_ACTIONS
-        .iter(deps.storage)?
-        .skip((start as usize) * (size as usize))
-        .take(size as usize);
+    // marked executed up front so the prop cannot be executed again from within its own messages
+    set_status(
+        deps.storage,
+        &proposal_id,
+        &mut prop,
+        ProposalStatus::Executed,
+    )?;
+    PROPOSALS.insert(deps.storage, &proposal_id, &prop)?;
+
+    EXECUTING_PROP.save(deps.storage, &proposal_id)?;
+    let run_msg = WasmMsg::Execute {
+        contract_addr: env.contract.address.to_string(),
+        code_hash: env.contract.code_hash,
+        msg: to_binary(&ExecuteMsg::RunProposal { proposal_id })?,
+        funds: vec![],
+    };
 
-    // Loop through Issuers and cnvert to ExportIssuer
-    loop {
-        let may_next_action = paginated_action_iter.next();
-        if let Some(element) = may_next_action {
-            let listing_pair = element?;
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_always(run_msg, EXECUTE_PROP_REPLY_ID))
+        .add_attribute("proposal_id", proposal_id.to_string()))
+}
 
-            action_list.push(listing_pair);
-        } else {
-            break;
-        }
+/// Returns Result<Response, ContractError>
+///
+/// dispatches the messages of the action being executed as submessages, so that the data they
+/// return can be passed back to `reply`
+///
+/// # Arguments
+///
+/// * `deps`        - DepsMut containing all the contract's external dependencies
+/// * `env`         - Env of contract's environment
+/// * `info`        - Carries the info of who sent the message and how much native funds were sent along
+/// * `proposal_id` - ID of the action being executed
+fn run_action(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    proposal_id: Uint128,
+) -> Result<Response, ContractError> {
+    if info.sender != env.contract.address
+        || EXECUTING_PROP.may_load(deps.storage)? != Some(proposal_id)
+    {
+        return Err(ContractError::Unauthorized {});
     }
 
-    Ok(to_binary(&QueryAnswer::AllActions {
-        actions: action_list,
-    })?)
+    let prop = match PROPOSALS.get(deps.storage, &proposal_id) {
+        Some(prop) => prop,
+        None => {
+            return Err(ContractError::CustomError {
+                val: "This propostion does not exist".to_string(),
+            })
+        }
+    };
+
+    Ok(Response::new().add_submessages(
+        prop.cosmos_msgs
+            .into_iter()
+            .map(|msg| SubMsg::reply_on_success(msg, PROP_MSG_REPLY_ID)),
+    ))
 }
 
-fn query_action(deps: Deps, id: Uint128, viewer: String) -> Result<Binary, ContractError> {
-    if !STAKEHOLDERS.contains(deps.storage, &viewer) {
-        return Err(ContractError::CustomError {
-            val: "You do not have a share in this contract".to_string(),
-        });
+/// Returns Result<Response, ContractError>
+///
+/// cancels an action that has not been executed, which can be done by its proposer or by the
+/// group itself through an action whose message calls this on the contract
+///
+/// # Arguments
+///
+/// * `deps` - DepsMut containing all the contract's external dependencies
+/// * `env`  - Env of contract's environment
+/// * `info` - Carries the info of who sent the message and how much native funds were sent along
+/// * `id`   - ID of the action being cancelled
+fn cancel_action(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: Uint128,
+) -> Result<Response, ContractError> {
+    let mut prop = match PROPOSALS.get(deps.storage, &id) {
+        Some(prop) => prop,
+        None => {
+            return Err(ContractError::CustomError {
+                val: "This propostion does not exist".to_string(),
+            })
+        }
+    };
+
+    if info.sender != prop.proposer && info.sender != env.contract.address {
+        return Err(ContractError::Unauthorized {});
     }
 
-    if !PENDING_ACTIONS.contains(deps.storage, &id) {
+    if !prop.status.is_pending() {
         return Err(ContractError::CustomError {
-            val: "This ID is not linked to a pending action".to_string(),
+            val: format!("This propostion is {} and cannot be cancelled", prop.status),
         });
     }
 
-    let action = PENDING_ACTIONS.get(deps.storage, &id).unwrap();
-    Ok(to_binary(&QueryAnswer::QueryAction { action })?)
+    set_status(deps.storage, &id, &mut prop, ProposalStatus::Cancelled)?;
+    PROPOSALS.insert(deps.storage, &id, &prop)?;
+
+    Ok(Response::new().add_attribute("status", prop.status.to_string()))
 }
 
-fn query_all_completed_actions(
-    deps: Deps,
-    start_page: Option<u32>,
-    page_size: Option<u32>,
-    viewer: String,
-) -> Result<Binary, ContractError> {
-    if !STAKEHOLDERS.contains(deps.storage, &viewer) {
-        return Err(ContractError::CustomError {
-            val: "You do not have a share in this contract".to_string(),
-        });
+/// Returns Result<Response, ContractError>
+///
+/// changes the stakeholders and their votes, which can only be done by the group itself through
+/// an action whose message calls this on the contract
+///
+/// # Arguments
+///
+/// * `deps`   - DepsMut containing all the contract's external dependencies
+/// * `env`    - Env of contract's environment
+/// * `info`   - Carries the info of who sent the message and how much native funds were sent along
+/// * `add`    - stakeholders to add or whose votes change, a stake of 0 removes them
+/// * `remove` - stakeholders to remove
+fn update_members(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    add: Vec<StakeAssignment>,
+    remove: Vec<String>,
+) -> Result<Response, ContractError> {
+    if info.sender != env.contract.address {
+        return Err(ContractError::Unauthorized {});
+    } else if CONFIG_KEY.load(deps.storage)?.staking.is_some() {
+        return Err(ContractError::StakedVotesOnly {});
     }
 
-    // Check for defaults
-    let start = start_page.unwrap_or(0);
-    let size = page_size.unwrap_or(DEFAULT_PAGE_SIZE);
-
-    // Prep empty List of Listing Data for response
-    let mut action_list: Vec<(Uint128, ExtActionProposition)> = vec![];
+    let mut total_votes = TOT_VOTES.load(deps.storage)?;
 
-    let mut paginated_action_iter = COMPLETED_ACTIONS
-        .iter(deps.storage)?
-        .skip((start as usize) * (size as usize))
-        .take(size as usize);
+    for holder in remove.iter() {
+        let holder = validate_stakeholder(deps.api, holder)?;
+        if let Some(votes) = STAKEHOLDERS.get(deps.storage, &holder) {
+            total_votes -= votes;
+            remove_member(deps.storage, &holder)?;
+        }
+    }
 
-    // Loop through Issuers and cnvert to ExportIssuer
-    loop {
-        let may_next_action = paginated_action_iter.next();
-        if let Some(element) = may_next_action {
-            let listing_pair = element?;
+    for stakeholder in add.iter() {
+        let holder = validate_stakeholder(deps.api, &stakeholder.holder)?;
+        if let Some(votes) = STAKEHOLDERS.get(deps.storage, &holder) {
+            total_votes -= votes;
+        }
 
-            action_list.push(listing_pair);
+        if stakeholder.stake.is_zero() {
+            remove_member(deps.storage, &holder)?;
         } else {
-            break;
+            STAKEHOLDERS.insert(deps.storage, &holder, &stakeholder.stake)?;
+            set_vesting(deps.storage, &holder, stakeholder)?;
+            MEMBER_ROLES.insert(deps.storage, &holder, &Role::all())?;
+            total_votes += stakeholder.stake;
         }
     }
 
-    Ok(to_binary(&QueryAnswer::AllActions {
-        actions: action_list,
-    })?)
+    // the new membership must still be able to pass props
+    let config = CONFIG_KEY.load(deps.storage)?;
+    let members = STAKEHOLDERS.get_len(deps.storage)?;
+    if members == 0 || total_votes.is_zero() {
+        return Err(ContractError::NoVotes {});
+    }
+    validate_threshold(&config.threshold, total_votes, members as usize)?;
+
+    TOT_VOTES.save(deps.storage, &total_votes)?;
+
+    Ok(Response::new()
+        .add_attribute("members", members.to_string())
+        .add_attribute("total_votes", total_votes.to_string()))
 }
 
-fn query_completed_action(
-    deps: Deps,
-    id: Uint128,
-    viewer: String,
-) -> Result<Binary, ContractError> {
-    if !STAKEHOLDERS.contains(deps.storage, &viewer) {
-        return Err(ContractError::CustomError {
-            val: "You do not have a share in this contract".to_string(),
+/// Returns Result<Response, ContractError>
+///
+/// replaces the roles of addresses, which can only be done by the group itself through an action
+/// whose message calls this on the contract
+///
+/// # Arguments
+///
+/// * `deps`          - DepsMut containing all the contract's external dependencies
+/// * `env`           - Env of contract's environment
+/// * `info`          - Carries the info of who sent the message and how much native funds were sent along
+/// * `assignments`   - addresses and the roles they now hold
+/// * `joining_roles` - optional roles of those who become stakeholders by receiving votes or staking
+fn update_roles(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    assignments: Vec<RoleAssignment>,
+    joining_roles: Option<Vec<Role>>,
+) -> Result<Response, ContractError> {
+    if info.sender != env.contract.address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    set_roles(deps.api, deps.storage, &assignments)?;
+    if let Some(joining_roles) = joining_roles {
+        let mut config = CONFIG_KEY.load(deps.storage)?;
+        config.joining_roles = joining_roles;
+        CONFIG_KEY.save(deps.storage, &config)?;
+    }
+
+    Ok(Response::new().add_attribute("assignments", assignments.len().to_string()))
+}
+
+/// Returns Result<Response, ContractError>
+///
+/// sets up a message to be dispatched at a fixed interval, which can only be done by the group
+/// itself through an action whose message calls this on the contract
+///
+/// # Arguments
+///
+/// * `deps`     - DepsMut containing all the contract's external dependencies
+/// * `env`      - Env of contract's environment
+/// * `info`     - Carries the info of who sent the message and how much native funds were sent along
+/// * `msg`      - message dispatched on every run
+/// * `start`    - time in seconds the first run is due
+/// * `interval` - seconds between runs
+/// * `runs`     - number of times the message is dispatched
+fn create_schedule(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: CosmosMsg,
+    start: u64,
+    interval: u64,
+    runs: u32,
+) -> Result<Response, ContractError> {
+    if info.sender != env.contract.address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if interval == 0 {
+        return Err(ContractError::InvalidSchedule {
+            reason: "interval must be at least one second".to_string(),
+        });
+    } else if runs == 0 {
+        return Err(ContractError::InvalidSchedule {
+            reason: "it must run at least once".to_string(),
+        });
+    } else if start < env.block.time.seconds() {
+        return Err(ContractError::InvalidSchedule {
+            reason: "start cannot be in the past".to_string(),
+        });
+    }
+
+    // the last run must be representable so that advancing to it never overflows
+    if interval
+        .checked_mul(runs as u64)
+        .and_then(|duration| duration.checked_add(start))
+        .is_none()
+    {
+        return Err(ContractError::InvalidSchedule {
+            reason: "the last run is too far in the future".to_string(),
         });
     }
 
-    if !COMPLETED_ACTIONS.contains(deps.storage, &id) {
+    let id = TOT_SCHEDULES.load(deps.storage)? + Uint128::from(1_u128);
+    TOT_SCHEDULES.save(deps.storage, &id)?;
+    SCHEDULES.insert(
+        deps.storage,
+        &id,
+        &Schedule {
+            msg,
+            interval,
+            next_run: start,
+            runs_left: runs,
+        },
+    )?;
+
+    Ok(Response::new().add_attribute("schedule_id", id.to_string()))
+}
+
+/// Returns Result<Response, ContractError>
+///
+/// stops a schedule, which can only be done by the group itself through an action whose message
+/// calls this on the contract
+///
+/// # Arguments
+///
+/// * `deps` - DepsMut containing all the contract's external dependencies
+/// * `env`  - Env of contract's environment
+/// * `info` - Carries the info of who sent the message and how much native funds were sent along
+/// * `id`   - ID of the schedule being cancelled
+fn cancel_schedule(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: Uint128,
+) -> Result<Response, ContractError> {
+    if info.sender != env.contract.address {
+        return Err(ContractError::Unauthorized {});
+    } else if !SCHEDULES.contains(deps.storage, &id) {
         return Err(ContractError::CustomError {
-            val: "This ID is not linked to a pending action".to_string(),
+            val: "This schedule does not exist".to_string(),
         });
     }
 
-    let action = COMPLETED_ACTIONS.get(deps.storage, &id).unwrap();
-    Ok(to_binary(&QueryAnswer::QueryAction { action })?)
+    SCHEDULES.remove(deps.storage, &id)?;
+
+    Ok(Response::new().add_attribute("schedule_id", id.to_string()))
 }
 
-//----------------------------------------- Helper functions----------------------------------
+/// Returns Result<Response, ContractError>
+///
+/// dispatches a schedule's message once its next run is due, removing the schedule after its
+/// last run. runs that were missed can be caught up on one call at a time
+///
+/// # Arguments
+///
+/// * `deps` - DepsMut containing all the contract's external dependencies
+/// * `env`  - Env of contract's environment
+/// * `id`   - ID of the schedule being run
+fn execute_schedule(deps: DepsMut, env: Env, id: Uint128) -> Result<Response, ContractError> {
+    let mut schedule = match SCHEDULES.get(deps.storage, &id) {
+        Some(schedule) => schedule,
+        None => {
+            return Err(ContractError::CustomError {
+                val: "This schedule does not exist".to_string(),
+            })
+        }
+    };
 
-/// Returns bool result of validating an address' viewing key
+    if env.block.time.seconds() < schedule.next_run {
+        return Err(ContractError::ScheduleNotDue {
+            next_run: schedule.next_run,
+        });
+    }
+
+    schedule.runs_left -= 1;
+    schedule.next_run += schedule.interval;
+    if schedule.runs_left == 0 {
+        SCHEDULES.remove(deps.storage, &id)?;
+    } else {
+        SCHEDULES.insert(deps.storage, &id, &schedule)?;
+    }
+
+    Ok(Response::new()
+        .add_message(schedule.msg)
+        .add_attribute("schedule_id", id.to_string())
+        .add_attribute("runs_left", schedule.runs_left.to_string()))
+}
+
+/// Returns Result<Response, ContractError>
+///
+/// sets how much of an asset a member can send without a prop, which can only be done by the
+/// group itself through an action whose message calls this on the contract
 ///
 /// # Arguments
 ///
-/// * `storage`     - a reference to the contract's storage
-/// * `account`     - a reference to the str whose key should be validated
-/// * `viewing_key` - String key used for authentication
-fn is_key_valid(storage: &dyn Storage, account: &str, viewing_key: String) -> bool {
-    ViewingKey::check(storage, account, &viewing_key).is_ok()
+/// * `deps`   - DepsMut containing all the contract's external dependencies
+/// * `env`    - Env of contract's environment
+/// * `info`   - Carries the info of who sent the message and how much native funds were sent along
+/// * `member` - address the allowance is given to
+/// * `asset`  - denom or token the allowance is in
+/// * `limit`  - amount that can be sent per window, 0 to remove the allowance
+/// * `window` - length of a window in seconds
+fn set_spend_allowance(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    member: String,
+    asset: Asset,
+    limit: Uint128,
+    window: u64,
+) -> Result<Response, ContractError> {
+    if info.sender != env.contract.address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let member = deps.api.addr_validate(&member)?;
+    if let Asset::Snip20 { token } = &asset {
+        deps.api.addr_validate(&token.address)?;
+    }
+    let allowances = SPEND_ALLOWANCES.add_suffix(member.as_bytes());
+    let key = asset.key().to_string();
+
+    if limit.is_zero() {
+        allowances.remove(deps.storage, &key)?;
+        return Ok(Response::new().add_attribute("member", member.as_str()));
+    } else if window == 0 || window > MAX_SPEND_WINDOW {
+        return Err(ContractError::CustomError {
+            val: format!(
+                "The window must be between 1 and {} seconds",
+                MAX_SPEND_WINDOW
+            ),
+        });
+    }
+
+    // what was already spent still counts against the new limit
+    let spends = match allowances.get(deps.storage, &key) {
+        Some(allowance) => allowance.spends,
+        None => vec![],
+    };
+    allowances.insert(
+        deps.storage,
+        &key,
+        &SpendAllowance {
+            asset,
+            limit,
+            window,
+            spends,
+        },
+    )?;
+
+    Ok(Response::new()
+        .add_attribute("member", member.as_str())
+        .add_attribute("limit", limit.to_string()))
+}
+
+/// Returns Result<Response, ContractError>
+///
+/// registers the treasury with a SNIP-20 token so that deposits of it are accepted and its balance
+/// can be viewed, which can only be done by the group itself through an action whose message calls
+/// this on the contract
+///
+/// # Arguments
+///
+/// * `deps`        - DepsMut containing all the contract's external dependencies
+/// * `env`         - Env of contract's environment
+/// * `info`        - Carries the info of who sent the message and how much native funds were sent along
+/// * `token`       - code hash and address of the token
+fn register_token(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    token: ContractInfo,
+) -> Result<Response, ContractError> {
+    if info.sender != env.contract.address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let address = deps.api.addr_validate(&token.address)?;
+    TOKENS.insert(deps.storage, &address.to_string(), &token)?;
+
+    let register_msg = Snip20Msg::RegisterReceive {
+        code_hash: env.contract.code_hash.clone(),
+        padding: None,
+    }
+    .to_cosmos_msg(token.code_hash.clone(), token.address.clone(), None)?;
+    // generated here rather than taken from the prop, whose messages anyone observing can read
+    let viewing_key = ViewingKey::create(
+        deps.storage,
+        &info,
+        &env,
+        env.contract.address.as_str(),
+        address.as_bytes(),
+    );
+    let key_msg = Snip20Msg::SetViewingKey {
+        key: viewing_key,
+        padding: None,
+    }
+    .to_cosmos_msg(token.code_hash, token.address, None)?;
+
+    Ok(Response::new()
+        .add_messages(vec![register_msg, key_msg])
+        .add_attribute("token", address.as_str()))
+}
+
+/// Returns Result<Response, ContractError>
+///
+/// sends funds within the sender's spending allowance, which caps what is sent in any window of
+/// time ending at the payment
+///
+/// # Arguments
+///
+/// * `deps`      - DepsMut containing all the contract's external dependencies
+/// * `env`       - Env of contract's environment
+/// * `info`      - Carries the info of who sent the message and how much native funds were sent along
+/// * `asset`     - denom or token address of the allowance being spent
+/// * `recipient` - address the funds are sent to
+/// * `amount`    - amount being sent
+fn spend(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    asset: String,
+    recipient: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let recipient = deps.api.addr_validate(&recipient)?;
+    let allowances = SPEND_ALLOWANCES.add_suffix(info.sender.as_bytes());
+    let mut allowance = match allowances.get(deps.storage, &asset) {
+        Some(allowance) => allowance,
+        None => {
+            return Err(ContractError::CustomError {
+                val: format!("You do not have an allowance in {}", asset),
+            })
+        }
+    };
+
+    let available = allowance.available(env.block.time);
+    if amount.is_zero() || amount > available {
+        return Err(ContractError::SpendLimitExceeded {
+            available,
+            required: amount,
+        });
+    }
+
+    // payments are kept until they leave the window, so their number is capped to bound gas
+    allowance.prune(env.block.time);
+    if allowance.spends.len() >= MAX_SPENDS_PER_WINDOW {
+        return Err(ContractError::TooManySpends {
+            max: MAX_SPENDS_PER_WINDOW,
+        });
+    }
+    allowance.spends.push(Spend {
+        amount,
+        at: env.block.time.seconds(),
+    });
+    allowances.insert(deps.storage, &asset, &allowance)?;
+
+    let send_msg = match allowance.asset {
+        Asset::Native { denom } => BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![Coin { denom, amount }],
+        }
+        .into(),
+        Asset::Snip20 { token } => Snip20Msg::Transfer {
+            recipient: recipient.to_string(),
+            amount,
+            memo: None,
+            padding: None,
+        }
+        .to_cosmos_msg(token.code_hash, token.address, None)?,
+    };
+
+    Ok(Response::new().add_message(send_msg).add_event(
+        Event::new("spend")
+            .add_attribute("member", info.sender.as_str())
+            .add_attribute("recipient", recipient.as_str())
+            .add_attribute("asset", asset)
+            .add_attribute("amount", amount.to_string()),
+    ))
+}
+
+/// Returns Result<Response, ContractError>
+///
+/// lets another address vote with the sender's votes, without transferring them
+///
+/// # Arguments
+///
+/// * `deps`     - DepsMut containing all the contract's external dependencies
+/// * `info`     - Carries the info of who sent the message and how much native funds were sent along
+/// * `delegate` - address voting on the sender's behalf, None to stop delegating
+fn delegate_votes(
+    deps: DepsMut,
+    info: MessageInfo,
+    delegate: Option<String>,
+) -> Result<Response, ContractError> {
+    let delegator = info.sender.to_string();
+    if !STAKEHOLDERS.contains(deps.storage, &delegator) {
+        return Err(ContractError::CustomError {
+            val: "You do not have a share in this contract".to_string(),
+        });
+    }
+    if delegate.is_some() {
+        require_role(deps.storage, &delegator, Role::Voter)?;
+    }
+
+    if let Some(previous) = DELEGATIONS.get(deps.storage, &delegator) {
+        DELEGATORS
+            .add_suffix(previous.as_bytes())
+            .remove(deps.storage, &delegator)?;
+        DELEGATIONS.remove(deps.storage, &delegator)?;
+    }
+
+    let mut response = Response::new();
+    if let Some(delegate) = delegate {
+        let delegate = deps.api.addr_validate(&delegate)?;
+        if delegate == info.sender {
+            return Err(ContractError::CustomError {
+                val: "You cannot delegate to yourself".to_string(),
+            });
+        }
+        require_role(deps.storage, delegate.as_str(), Role::Voter)?;
+
+        DELEGATIONS.insert(deps.storage, &delegator, &delegate)?;
+        DELEGATORS
+            .add_suffix(delegate.as_bytes())
+            .insert(deps.storage, &delegator, &true)?;
+        response = response.add_attribute("delegate", delegate.as_str());
+    }
+
+    Ok(response)
+}
+
+/// Returns Result<Response, ContractError>
+///
+/// marks open and passed actions whose time limit ran out as expired
+///
+/// # Arguments
+///
+/// * `deps`       - DepsMut containing all the contract's external dependencies
+/// * `env`        - Env of contract's environment
+/// * `info`       - Carries the info of who sent the message and how much native funds were sent along
+/// * `start_page` - optional page of pending actions to start checking from
+/// * `page_size`  - optional number of pending actions to check
+fn purge_expired_actions(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    start_page: Option<u32>,
+    page_size: Option<u32>,
+) -> Result<Response, ContractError> {
+    if roles_of(deps.storage, info.sender.as_str()).is_empty() {
+        return Err(ContractError::Unauthorized {});
+    }
+    let config = CONFIG_KEY.load(deps.storage)?;
+
+    // Check for defaults
+    let start = start_page.unwrap_or(0);
+    let size = page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+
+    // closed actions are never pending again, so only pending ones are checked
+    let ids = PENDING_PROPS.paging_keys(deps.storage, start, size)?;
+    let expired_count = expire_props(deps.storage, &config, &env, ids)?;
+
+    Ok(Response::new().add_attribute("expired", expired_count.to_string()))
+}
+
+/// Returns Result<Response, ContractError>
+///
+/// create a viewing key
+///
+/// # Arguments
+///
+/// * `deps`    - DepsMut containing all the contract's external dependencies
+/// * `env`     - Env of contract's environment
+/// * `info`    - Carries the info of who sent the message and how much native funds were sent along
+/// * `entropy` - string to be used as an entropy source for randomization
+fn try_create_key(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    entropy: String,
+) -> Result<Response, ContractError> {
+    let key = ViewingKey::create(
+        deps.storage,
+        &info,
+        &env,
+        info.sender.as_str(),
+        entropy.as_bytes(),
+    );
+
+    Ok(Response::new().add_attribute("viewing_key", key))
+}
+
+/// Returns Result<Response, ContractError>
+///
+/// sets the viewing key
+///
+/// # Arguments
+///
+/// * `deps` - DepsMut containing all the contract's external dependencies
+/// * `info` - Carries the info of who sent the message and how much native funds were sent along
+/// * `key`  - string slice to be used as the viewing key
+fn try_set_key(deps: DepsMut, info: MessageInfo, key: &str) -> Result<Response, ContractError> {
+    ViewingKey::set(deps.storage, info.sender.as_str(), key);
+
+    Ok(Response::new().add_attribute("viewing_key", key))
+}
+
+fn revoke_permit(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    permit_name: String,
+) -> Result<Response, ContractError> {
+    RevokedPermits::revoke_permit(
+        deps.storage,
+        PREFIX_REVOKED_PERMITS,
+        info.sender.as_ref(),
+        &permit_name,
+    );
+
+    Ok(Response::new())
+}
+
+//-------------------------------------------- REPLIES ---------------------------------
+
+#[entry_point]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        EXECUTE_PROP_REPLY_ID => record_execution(deps, msg.result),
+        PROP_MSG_REPLY_ID => forward_data(msg.result),
+        id => Err(ContractError::UnexpectedReplyId { id }),
+    }
+}
+
+/// Returns Result<Response, ContractError>
+///
+/// sets the data returned by one of an action's messages as the data of the execution, so the
+/// last message returning any is what gets recorded
+///
+/// # Arguments
+///
+/// * `result` - result of the submessage that dispatched the message
+fn forward_data(result: SubMsgResult) -> Result<Response, ContractError> {
+    match result {
+        SubMsgResult::Ok(SubMsgResponse {
+            data: Some(data), ..
+        }) => Ok(Response::new().set_data(data)),
+        SubMsgResult::Ok(_) => Ok(Response::new()),
+        SubMsgResult::Err(error) => Err(ContractError::CustomError { val: error }),
+    }
+}
+
+/// Returns Result<Response, ContractError>
+///
+/// records the outcome of dispatching an action's messages. a failed action stays passed, and can
+/// be executed again, until it runs out of attempts
+///
+/// # Arguments
+///
+/// * `deps`   - DepsMut containing all the contract's external dependencies
+/// * `result` - result of the submessage that dispatched the action's messages
+fn record_execution(deps: DepsMut, result: SubMsgResult) -> Result<Response, ContractError> {
+    let proposal_id = match EXECUTING_PROP.may_load(deps.storage)? {
+        Some(proposal_id) => proposal_id,
+        None => {
+            return Err(ContractError::ParseReplyError {
+                id: EXECUTE_PROP_REPLY_ID,
+            })
+        }
+    };
+    EXECUTING_PROP.remove(deps.storage);
+
+    let config = CONFIG_KEY.load(deps.storage)?;
+    let mut prop = match PROPOSALS.get(deps.storage, &proposal_id) {
+        Some(prop) => prop,
+        None => {
+            return Err(ContractError::ParseReplyError {
+                id: EXECUTE_PROP_REPLY_ID,
+            })
+        }
+    };
+    prop.execution_attempts += 1;
+    match result {
+        SubMsgResult::Ok(response) => {
+            set_status(
+                deps.storage,
+                &proposal_id,
+                &mut prop,
+                ProposalStatus::Executed,
+            )?;
+            prop.execution_result = Some(ExecutionResult::Success {
+                data: response.data,
+            });
+        }
+        SubMsgResult::Err(error) => {
+            let status = if prop.execution_attempts < config.max_execution_attempts {
+                ProposalStatus::Passed
+            } else {
+                ProposalStatus::ExecutionFailed
+            };
+            set_status(deps.storage, &proposal_id, &mut prop, status)?;
+            prop.execution_result = Some(ExecutionResult::Failure { error });
+        }
+    }
+    PROPOSALS.insert(deps.storage, &proposal_id, &prop)?;
+
+    Ok(Response::new()
+        .add_attribute("proposal_id", proposal_id.to_string())
+        .add_attribute("status", prop.status.to_string())
+        .add_attribute("attempts", prop.execution_attempts.to_string()))
+}
+
+// ---------------------------------------- QUERIES --------------------------------------
+
+#[entry_point]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
+    match msg {
+        QueryMsg::Config {} => query_config(deps),
+        QueryMsg::WithPermit { permit, query } => permit_queries(deps, env, permit, query),
+        _ => viewing_keys_queries(deps, env, msg),
+    }
+}
+
+/// Returns QueryResult from validating a permit and then using its creator's address when
+/// performing the specified query
+///
+/// # Arguments
+///
+/// * `deps` - a reference to Extern containing all the contract's external dependencies
+/// * `env` - Env of contract's environment
+/// * `permit` - the permit used to authentic the query
+/// * `query` - the query to perform
+fn permit_queries(
+    deps: Deps,
+    env: Env,
+    permit: Permit,
+    query: QueryWithPermit,
+) -> Result<Binary, ContractError> {
+    // Validate permit content
+    let config = CONFIG_KEY.load(deps.storage)?;
+
+    let viewer = validate(
+        deps,
+        PREFIX_REVOKED_PERMITS,
+        &permit,
+        config.contract_address.to_string(),
+        None,
+    )?;
+
+    // Permit validated! We can now execute the query.
+    match query {
+        QueryWithPermit::AllActions {
+            start_page,
+            page_size,
+            status,
+        } => {
+            if !permit.check_permission(&TokenPermissions::Balance) {
+                return Err(ContractError::Unauthorized {});
+            }
+
+            query_all_actions(deps, start_page, page_size, status, viewer)
+        }
+        QueryWithPermit::FailedActions {
+            start_page,
+            page_size,
+        } => {
+            if !permit.check_permission(&TokenPermissions::Balance) {
+                return Err(ContractError::Unauthorized {});
+            }
+
+            query_failed_actions(deps, start_page, page_size, viewer)
+        }
+        QueryWithPermit::QueryAction { id } => {
+            if !permit.check_permission(&TokenPermissions::Balance) {
+                return Err(ContractError::Unauthorized {});
+            }
+
+            query_action(deps, id, viewer)
+        }
+        QueryWithPermit::Stakeholders {
+            start_page,
+            page_size,
+        } => {
+            if !permit.check_permission(&TokenPermissions::Balance) {
+                return Err(ContractError::Unauthorized {});
+            }
+
+            query_stakeholders(deps, start_page, page_size, viewer)
+        }
+        QueryWithPermit::Schedules {
+            start_page,
+            page_size,
+        } => {
+            if !permit.check_permission(&TokenPermissions::Balance) {
+                return Err(ContractError::Unauthorized {});
+            }
+
+            query_schedules(deps, start_page, page_size, viewer)
+        }
+        QueryWithPermit::Tokens {} => {
+            if !permit.check_permission(&TokenPermissions::Balance) {
+                return Err(ContractError::Unauthorized {});
+            }
+
+            query_tokens(deps, viewer)
+        }
+        QueryWithPermit::Deposits {
+            start_page,
+            page_size,
+        } => {
+            if !permit.check_permission(&TokenPermissions::Balance) {
+                return Err(ContractError::Unauthorized {});
+            }
+
+            query_deposits(deps, start_page, page_size, viewer)
+        }
+        QueryWithPermit::Stakeholder { address } => {
+            if !permit.check_permission(&TokenPermissions::Balance) {
+                return Err(ContractError::Unauthorized {});
+            }
+
+            query_stakeholder(deps, env, address, viewer)
+        }
+        QueryWithPermit::SpendAllowances { member } => {
+            if !permit.check_permission(&TokenPermissions::Allowance) {
+                return Err(ContractError::Unauthorized {});
+            }
+
+            query_spend_allowances(deps, member, viewer)
+        }
+        QueryWithPermit::VoteAllowance { owner, spender } => {
+            if !permit.check_permission(&TokenPermissions::Allowance) {
+                return Err(ContractError::Unauthorized {});
+            }
+
+            query_vote_allowance(deps, env, owner, spender, viewer)
+        }
+    }
+}
+
+pub fn viewing_keys_queries(deps: Deps, env: Env, msg: QueryMsg) -> Result<Binary, ContractError> {
+    let (address, key) = msg.get_validation_params();
+
+    if !is_key_valid(deps.storage, &address, key) {
+        Err(ContractError::Unauthorized {})
+    } else {
+        match msg {
+            // Base
+            QueryMsg::AllActions {
+                viewer,
+                key: _,
+                start_page,
+                page_size,
+                status,
+            } => query_all_actions(deps, start_page, page_size, status, viewer),
+            QueryMsg::QueryAction { viewer, key: _, id } => query_action(deps, id, viewer),
+            QueryMsg::FailedActions {
+                viewer,
+                key: _,
+                start_page,
+                page_size,
+            } => query_failed_actions(deps, start_page, page_size, viewer),
+            QueryMsg::Stakeholders {
+                viewer,
+                key: _,
+                start_page,
+                page_size,
+            } => query_stakeholders(deps, start_page, page_size, viewer),
+            QueryMsg::Schedules {
+                viewer,
+                key: _,
+                start_page,
+                page_size,
+            } => query_schedules(deps, start_page, page_size, viewer),
+            QueryMsg::Tokens { viewer, key: _ } => query_tokens(deps, viewer),
+            QueryMsg::Deposits {
+                viewer,
+                key: _,
+                start_page,
+                page_size,
+            } => query_deposits(deps, start_page, page_size, viewer),
+            QueryMsg::Stakeholder {
+                viewer,
+                key: _,
+                address,
+            } => query_stakeholder(deps, env, address, viewer),
+            QueryMsg::VoteAllowance {
+                viewer,
+                key: _,
+                owner,
+                spender,
+            } => query_vote_allowance(deps, env, owner, spender, viewer),
+            QueryMsg::SpendAllowances {
+                viewer,
+                key: _,
+                member,
+            } => query_spend_allowances(deps, member, viewer),
+
+            _ => panic!("This query type does not require authentication"),
+        }
+    }
+}
+
+fn query_config(deps: Deps) -> Result<Binary, ContractError> {
+    let config = CONFIG_KEY.load(deps.storage)?;
+    Ok(to_binary(&QueryAnswer::Config { config })?)
+}
+
+fn query_all_actions(
+    deps: Deps,
+    start_page: Option<u32>,
+    page_size: Option<u32>,
+    status: Option<ProposalStatus>,
+    viewer: String,
+) -> Result<Binary, ContractError> {
+    require_role(deps.storage, &viewer, Role::Observer)?;
+
+    // Check for defaults
+    let start = start_page.unwrap_or(0);
+    let size = page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+
+    // Prep empty List of Listing Data for response
+    let mut action_list: Vec<(Uint128, ExtActionProposition)> = vec![];
+
+    let mut paginated_action_iter = PROPOSALS
+        .iter(deps.storage)?
+        .filter(|element| match (&status, element) {
+            (Some(status), Ok((_, action))) => action.status == *status,
+            _ => true,
+        })
+        .skip((start as usize) * (size as usize))
+        .take(size as usize);
+
+    // Loop through Issuers and cnvert to ExportIssuer
+    loop {
+        let may_next_action = paginated_action_iter.next();
+        if let Some(element) = may_next_action {
+            let listing_pair = element?;
+
+            action_list.push(listing_pair);
+        } else {
+            break;
+        }
+    }
+
+    Ok(to_binary(&QueryAnswer::AllActions {
+        actions: action_list,
+    })?)
+}
+
+fn query_failed_actions(
+    deps: Deps,
+    start_page: Option<u32>,
+    page_size: Option<u32>,
+    viewer: String,
+) -> Result<Binary, ContractError> {
+    require_role(deps.storage, &viewer, Role::Observer)?;
+
+    // Check for defaults
+    let start = start_page.unwrap_or(0);
+    let size = page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+
+    // failed actions that were retried successfully are left out
+    let actions = PROPOSALS
+        .iter(deps.storage)?
+        .filter(|element| match element {
+            Ok((_, action)) => matches!(
+                action.execution_result,
+                Some(ExecutionResult::Failure { .. })
+            ),
+            Err(_) => true,
+        })
+        .skip((start as usize) * (size as usize))
+        .take(size as usize)
+        .collect::<StdResult<Vec<(Uint128, ExtActionProposition)>>>()?;
+
+    Ok(to_binary(&QueryAnswer::AllActions { actions })?)
+}
+
+fn query_action(deps: Deps, id: Uint128, viewer: String) -> Result<Binary, ContractError> {
+    require_role(deps.storage, &viewer, Role::Observer)?;
+
+    let action = match PROPOSALS.get(deps.storage, &id) {
+        Some(action) => action,
+        None => {
+            return Err(ContractError::CustomError {
+                val: "This ID is not linked to an action".to_string(),
+            })
+        }
+    };
+    Ok(to_binary(&QueryAnswer::QueryAction { action })?)
+}
+
+fn query_stakeholders(
+    deps: Deps,
+    start_page: Option<u32>,
+    page_size: Option<u32>,
+    viewer: String,
+) -> Result<Binary, ContractError> {
+    require_role(deps.storage, &viewer, Role::Observer)?;
+
+    // Check for defaults
+    let start = start_page.unwrap_or(0);
+    let size = page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+
+    let stakeholders = STAKEHOLDERS
+        .paging(deps.storage, start, size)?
+        .into_iter()
+        .map(|(holder, stake)| StakeAssignment {
+            vesting: VESTING.get(deps.storage, &holder),
+            holder,
+            stake,
+        })
+        .collect();
+
+    Ok(to_binary(&QueryAnswer::Stakeholders {
+        stakeholders,
+        count: STAKEHOLDERS.get_len(deps.storage)?,
+        total_votes: TOT_VOTES.load(deps.storage)?,
+    })?)
+}
+
+fn query_schedules(
+    deps: Deps,
+    start_page: Option<u32>,
+    page_size: Option<u32>,
+    viewer: String,
+) -> Result<Binary, ContractError> {
+    require_role(deps.storage, &viewer, Role::Observer)?;
+
+    // Check for defaults
+    let start = start_page.unwrap_or(0);
+    let size = page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+
+    Ok(to_binary(&QueryAnswer::Schedules {
+        schedules: SCHEDULES.paging(deps.storage, start, size)?,
+        count: SCHEDULES.get_len(deps.storage)?,
+    })?)
+}
+
+fn query_tokens(deps: Deps, viewer: String) -> Result<Binary, ContractError> {
+    require_role(deps.storage, &viewer, Role::Observer)?;
+
+    let tokens = TOKENS
+        .iter(deps.storage)?
+        .map(|element| element.map(|(_, token)| token))
+        .collect::<StdResult<Vec<ContractInfo>>>()?;
+    Ok(to_binary(&QueryAnswer::Tokens { tokens })?)
+}
+
+fn query_deposits(
+    deps: Deps,
+    start_page: Option<u32>,
+    page_size: Option<u32>,
+    viewer: String,
+) -> Result<Binary, ContractError> {
+    require_role(deps.storage, &viewer, Role::Observer)?;
+
+    // Check for defaults
+    let start = start_page.unwrap_or(0);
+    let size = page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+
+    Ok(to_binary(&QueryAnswer::Deposits {
+        deposits: DEPOSITS.paging(deps.storage, start, size)?,
+        count: DEPOSITS.get_len(deps.storage)?,
+    })?)
+}
+
+fn query_stakeholder(
+    deps: Deps,
+    env: Env,
+    address: String,
+    viewer: String,
+) -> Result<Binary, ContractError> {
+    require_role(deps.storage, &viewer, Role::Observer)?;
+
+    let votes = STAKEHOLDERS.get(deps.storage, &address).unwrap_or_default();
+    let vested_votes = vested_votes(deps.storage, &address, votes, env.block.time);
+    Ok(to_binary(&QueryAnswer::Stakeholder {
+        votes,
+        vested_votes,
+        unvested_votes: votes - vested_votes,
+        delegate: DELEGATIONS.get(deps.storage, &address),
+        roles: roles_of(deps.storage, &address),
+        unbonding: UNBONDING.get(deps.storage, &address).unwrap_or_default(),
+        address,
+    })?)
+}
+
+fn query_vote_allowance(
+    deps: Deps,
+    env: Env,
+    owner: String,
+    spender: String,
+    viewer: String,
+) -> Result<Binary, ContractError> {
+    if viewer != owner && viewer != spender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let allowance = VOTE_ALLOWANCES
+        .add_suffix(owner.as_bytes())
+        .get(deps.storage, &spender)
+        .filter(|allowance| !allowance.is_expired(env.block.time))
+        .unwrap_or_default();
+    Ok(to_binary(&QueryAnswer::VoteAllowance {
+        owner,
+        spender,
+        amount: allowance.amount,
+        expiration: allowance.expiration,
+    })?)
+}
+
+fn query_spend_allowances(
+    deps: Deps,
+    member: String,
+    viewer: String,
+) -> Result<Binary, ContractError> {
+    if viewer != member {
+        require_role(deps.storage, &viewer, Role::Observer)?;
+    }
+
+    let allowances = SPEND_ALLOWANCES
+        .add_suffix(member.as_bytes())
+        .iter(deps.storage)?
+        .map(|element| element.map(|(_, allowance)| allowance))
+        .collect::<StdResult<Vec<SpendAllowance>>>()?;
+    Ok(to_binary(&QueryAnswer::SpendAllowances {
+        member,
+        allowances,
+    })?)
+}
+
+//----------------------------------------- Helper functions----------------------------------
+
+/// Returns Result<(), ContractError>
+///
+/// checks that a threshold can be reached by the stakeholders it applies to
+///
+/// # Arguments
+///
+/// * `threshold`    - a reference to the threshold being validated
+/// * `total_votes`  - total number of votes availible
+/// * `stakeholders` - number of stakeholders
+fn validate_threshold(
+    threshold: &Threshold,
+    total_votes: Uint128,
+    stakeholders: usize,
+) -> Result<(), ContractError> {
+    match threshold {
+        Threshold::AbsoluteWeight { weight } => {
+            if weight.is_zero() || *weight > total_votes {
+                return Err(ContractError::InvalidThreshold {
+                    reason: format!("weight must be between 1 and {}", total_votes),
+                });
+            }
+        }
+        Threshold::Percentage { percent } => {
+            if percent.is_zero() || *percent > Decimal::one() {
+                return Err(ContractError::InvalidThreshold {
+                    reason: "percent must be greater than 0 and at most 1".to_string(),
+                });
+            }
+        }
+        Threshold::Signers { count } => {
+            if *count == 0 || *count as usize > stakeholders {
+                return Err(ContractError::InvalidThreshold {
+                    reason: format!("signer count must be between 1 and {}", stakeholders),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns Result<(), ContractError>
+///
+/// moves votes between stakeholders, removing the sender once they have none left
+///
+/// # Arguments
+///
+/// * `storage`   - a mutable reference to the contract's storage
+/// * `sender`    - a reference to the address the votes are taken from
+/// * `recipient` - a reference to the address the votes are given to
+/// * `num_votes` - number of votes being moved, which must already be vested
+/// * `now`       - current block time
+fn move_votes(
+    storage: &mut dyn Storage,
+    sender: &str,
+    recipient: &str,
+    num_votes: Uint128,
+    now: Timestamp,
+) -> Result<(), ContractError> {
+    if num_votes.is_zero() {
+        return Err(ContractError::CustomError {
+            val: "You cannot transfer zero votes".to_string(),
+        });
+    } else if sender == recipient {
+        return Err(ContractError::CustomError {
+            val: "You cannot transfer votes to yourself".to_string(),
+        });
+    }
+
+    let sender_votes = STAKEHOLDERS
+        .get(storage, &sender.to_string())
+        .unwrap_or_default();
+    if num_votes > vested_votes(storage, sender, sender_votes, now) {
+        return Err(ContractError::CustomError {
+            val: "You cannot transfer a larger share than you have vested".to_string(),
+        });
+    } else if num_votes == sender_votes {
+        remove_stakeholder(storage, sender)?;
+    } else {
+        check_min_stake(storage, sender_votes - num_votes)?;
+        STAKEHOLDERS.insert(storage, &sender.to_string(), &(sender_votes - num_votes))?;
+    }
+
+    let reciever_votes = STAKEHOLDERS
+        .get(storage, &recipient.to_string())
+        .unwrap_or_default();
+    check_min_stake(storage, reciever_votes + num_votes)?;
+    if reciever_votes.is_zero() {
+        grant_joining_roles(storage, recipient)?;
+    }
+    STAKEHOLDERS.insert(
+        storage,
+        &recipient.to_string(),
+        &(reciever_votes + num_votes),
+    )?;
+
+    Ok(())
+}
+
+/// Returns Result<(), ContractError>
+///
+/// stores or clears the vesting schedule of a stakeholder's votes
+///
+/// # Arguments
+///
+/// * `storage`     - a mutable reference to the contract's storage
+/// * `holder`      - a reference to the stakeholder's address
+/// * `stakeholder` - a reference to the stakeholder's assignment
+fn set_vesting(
+    storage: &mut dyn Storage,
+    holder: &str,
+    stakeholder: &StakeAssignment,
+) -> Result<(), ContractError> {
+    let vesting = match &stakeholder.vesting {
+        Some(vesting) => vesting,
+        None => {
+            VESTING.remove(storage, &holder.to_string())?;
+            return Ok(());
+        }
+    };
+
+    if vesting.amount > stakeholder.stake {
+        return Err(ContractError::InvalidVestingSchedule {
+            reason: "amount cannot exceed the stake".to_string(),
+        });
+    } else if vesting.start > vesting.cliff || vesting.cliff > vesting.end {
+        return Err(ContractError::InvalidVestingSchedule {
+            reason: "start, cliff and end must be in order".to_string(),
+        });
+    } else if vesting.start == vesting.end {
+        return Err(ContractError::InvalidVestingSchedule {
+            reason: "end must be after start".to_string(),
+        });
+    }
+
+    VESTING.insert(storage, &holder.to_string(), vesting)?;
+    Ok(())
+}
+
+/// Returns Result<String, ContractError> of a stakeholder's validated address
+///
+/// # Arguments
+///
+/// * `api`    - a reference to the Api used to validate the address
+/// * `holder` - the stakeholder's address as given
+fn validate_stakeholder(api: &dyn Api, holder: &str) -> Result<String, ContractError> {
+    match api.addr_validate(holder) {
+        Ok(address) => Ok(address.to_string()),
+        Err(_) => Err(ContractError::InvalidStakeholder {
+            holder: holder.to_string(),
+        }),
+    }
+}
+
+/// Returns Result<(), ContractError>
+///
+/// checks that a stakeholder's new votes reach the minimum stake, when votes are staked
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `votes`   - the stakeholder's votes after the change, which must not be zero
+fn check_min_stake(storage: &dyn Storage, votes: Uint128) -> Result<(), ContractError> {
+    if let Some(staking) = CONFIG_KEY.load(storage)?.staking {
+        if votes < staking.min_stake {
+            return Err(ContractError::BelowMinimumStake {
+                min: staking.min_stake,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Returns Uint128 of a stakeholder's votes that have vested
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `holder`  - a reference to the stakeholder's address
+/// * `votes`   - the stakeholder's votes, vested or not
+/// * `now`     - current block time
+fn vested_votes(storage: &dyn Storage, holder: &str, votes: Uint128, now: Timestamp) -> Uint128 {
+    match VESTING.get(storage, &holder.to_string()) {
+        Some(vesting) => votes.saturating_sub(vesting.unvested(now)),
+        None => votes,
+    }
+}
+
+/// Returns StdResult<()>
+///
+/// removes a stakeholder along with their delegation and the roles they got by joining
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to the contract's storage
+/// * `holder`  - a reference to the stakeholder's address
+fn remove_stakeholder(storage: &mut dyn Storage, holder: &str) -> StdResult<()> {
+    STAKEHOLDERS.remove(storage, &holder.to_string())?;
+    VESTING.remove(storage, &holder.to_string())?;
+    MEMBER_ROLES.remove(storage, &holder.to_string())?;
+    if let Some(delegate) = DELEGATIONS.get(storage, &holder.to_string()) {
+        DELEGATORS
+            .add_suffix(delegate.as_bytes())
+            .remove(storage, &holder.to_string())?;
+        DELEGATIONS.remove(storage, &holder.to_string())?;
+    }
+    Ok(())
+}
+
+/// Returns StdResult<()>
+///
+/// removes a stakeholder the group voted out, along with the roles and spending allowances the
+/// group gave them
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to the contract's storage
+/// * `holder`  - a reference to the stakeholder's address
+fn remove_member(storage: &mut dyn Storage, holder: &str) -> StdResult<()> {
+    remove_stakeholder(storage, holder)?;
+    ROLES.remove(storage, &holder.to_string())?;
+
+    let allowances = SPEND_ALLOWANCES.add_suffix(holder.as_bytes());
+    let assets = allowances
+        .iter_keys(storage)?
+        .collect::<StdResult<Vec<String>>>()?;
+    for asset in assets.iter() {
+        allowances.remove(storage, asset)?;
+    }
+    Ok(())
+}
+
+/// Returns Result<(), ContractError>
+///
+/// checks that an address holds a role
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `address` - a reference to the address being checked
+/// * `role`    - the role it must hold
+fn require_role(storage: &dyn Storage, address: &str, role: Role) -> Result<(), ContractError> {
+    if !roles_of(storage, address).contains(&role) {
+        return Err(ContractError::MissingRole { role });
+    }
+    Ok(())
+}
+
+/// Returns Vec<Role> of the roles an address holds, which are the ones the group set for it or,
+/// failing that, the ones it got by becoming a stakeholder
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `address` - a reference to the address being checked
+fn roles_of(storage: &dyn Storage, address: &str) -> Vec<Role> {
+    ROLES
+        .get(storage, &address.to_string())
+        .or_else(|| MEMBER_ROLES.get(storage, &address.to_string()))
+        .unwrap_or_default()
+}
+
+/// Returns StdResult<()>
+///
+/// gives the joining roles set by the group to an address that just became a stakeholder
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to the contract's storage
+/// * `holder`  - a reference to the new stakeholder's address
+fn grant_joining_roles(storage: &mut dyn Storage, holder: &str) -> StdResult<()> {
+    let joining_roles = CONFIG_KEY.load(storage)?.joining_roles;
+    if !joining_roles.is_empty() {
+        MEMBER_ROLES.insert(storage, &holder.to_string(), &joining_roles)?;
+    }
+    Ok(())
+}
+
+/// Returns StdResult<()>
+///
+/// replaces the roles of addresses, an empty list leaving them with none
+///
+/// # Arguments
+///
+/// * `api`         - a reference to the Api used to validate addresses
+/// * `storage`     - a mutable reference to the contract's storage
+/// * `assignments` - a reference to the addresses and the roles they now hold
+fn set_roles(
+    api: &dyn Api,
+    storage: &mut dyn Storage,
+    assignments: &[RoleAssignment],
+) -> StdResult<()> {
+    for assignment in assignments.iter() {
+        let address = api.addr_validate(&assignment.address)?.to_string();
+        let mut roles = assignment.roles.clone();
+        roles.dedup();
+        ROLES.insert(storage, &address, &roles)?;
+    }
+    Ok(())
+}
+
+/// Returns Result<(), ContractError>
+///
+/// checks that a text field's length, in characters, is within bounds
+///
+/// # Arguments
+///
+/// * `field` - name of the field reported in the error
+/// * `value` - a reference to the field's text
+/// * `min`   - minimum number of characters
+/// * `max`   - maximum number of characters
+fn validate_length(field: &str, value: &str, min: usize, max: usize) -> Result<(), ContractError> {
+    let len = value.chars().count();
+    if len < min || len > max {
+        return Err(ContractError::InvalidLength {
+            field: field.to_string(),
+            min,
+            max,
+        });
+    }
+    Ok(())
+}
+
+/// Returns Result<bool, ContractError> of whether a stakeholder's ballot on a proposal changed
+///
+/// A stakeholder's own ballot replaces one cast by their delegate, while a delegate skips
+/// stakeholders whose ballot was cast by someone else. Ballots cannot change once cast if votes
+/// are locked. A skipped ballot is an error only if it is `strict`.
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to the contract's storage
+/// * `config`  - a reference to the contract's config
+/// * `prop`    - a mutable reference to the proposal whose tally is updated
+/// * `id`      - ID of the proposal
+/// * `owner`   - address of the stakeholder whose votes are used
+/// * `votes`   - votes the stakeholder held when the proposal was made
+/// * `cast_by` - a reference to the address casting the ballot
+/// * `vote`    - ballot option chosen, None to retract the ballot
+/// * `strict`  - whether skipping the ballot is an error
+#[allow(clippy::too_many_arguments)]
+fn apply_ballot(
+    storage: &mut dyn Storage,
+    config: &Config,
+    prop: &mut ExtActionProposition,
+    id: Uint128,
+    owner: &str,
+    votes: Uint128,
+    cast_by: &Addr,
+    vote: Option<Vote>,
+    strict: bool,
+) -> Result<bool, ContractError> {
+    let vote_record = VOTE_RECORD.add_suffix(&id.to_be_bytes());
+    let previous = vote_record.get(storage, &owner.to_string());
+    let is_direct = cast_by.as_str() == owner;
+
+    let skip_reason = match (&previous, vote) {
+        (Some(_), _) if config.lock_votes => Some("You have already voted on this prop"),
+        (Some(previous), _) if !is_direct && previous.cast_by != *cast_by => {
+            Some("This ballot was cast by someone else")
+        }
+        (Some(previous), Some(vote)) if previous.vote == vote && previous.cast_by == *cast_by => {
+            Some("You have already cast this vote on this prop")
+        }
+        (None, None) => Some("You have not voted on this prop"),
+        _ => None,
+    };
+    if let Some(reason) = skip_reason {
+        if strict {
+            return Err(ContractError::CustomError {
+                val: reason.to_string(),
+            });
+        }
+        return Ok(false);
+    }
+
+    if let Some(previous) = previous {
+        prop.tally.remove(previous.vote, votes);
+        if previous.vote == Vote::Yes {
+            prop.confirmed_signers -= 1;
+        }
+    }
+
+    // records voting roll
+    match vote {
+        Some(vote) => {
+            let ballot = Ballot {
+                vote,
+                cast_by: cast_by.clone(),
+            };
+            vote_record.insert(storage, &owner.to_string(), &ballot)?;
+            prop.tally.add(vote, votes);
+            if vote == Vote::Yes {
+                prop.confirmed_signers += 1;
+            }
+        }
+        None => vote_record.remove(storage, &owner.to_string())?,
+    }
+
+    Ok(true)
+}
+
+/// Returns bool result of checking ballots against the quorum and threshold
+///
+/// # Arguments
+///
+/// * `config`      - a reference to the contract's config
+/// * `tally`       - a reference to the ballots cast on the proposal
+/// * `signers`     - number of stakeholders supporting the proposal
+/// * `total_votes` - total number of votes availible
+fn is_passed(config: &Config, tally: &Tally, signers: u32, total_votes: Uint128) -> bool {
+    if let Some(quorum) = config.quorum {
+        if total_votes.is_zero() || Decimal::from_ratio(tally.total(), total_votes) < quorum {
+            return false;
+        }
+    }
+
+    config.threshold.is_met(tally, signers, total_votes)
+}
+
+/// Returns bool result of checking whether enough of a proposal's votes vetoed it to reject it
+///
+/// # Arguments
+///
+/// * `config` - a reference to the contract's config
+/// * `prop`   - a reference to the proposal being checked
+fn is_vetoed(config: &Config, prop: &ExtActionProposition) -> bool {
+    !prop.total_votes.is_zero()
+        && Decimal::from_ratio(prop.tally.veto, prop.total_votes) >= config.veto_threshold
+}
+
+/// Returns StdResult<ProposalStatus> of a proposal after a ballot changes, which is rejected if it
+/// was vetoed, passed if it has the votes, rejected if it could not get them even with every
+/// remaining stakeholder in favor, and open otherwise
+///
+/// # Arguments
+///
+/// * `storage` - a reference to the contract's storage
+/// * `config`  - a reference to the contract's config
+/// * `prop`    - a reference to the proposal being checked
+/// * `id`      - ID of the proposal being checked
+fn tally_status(
+    storage: &dyn Storage,
+    config: &Config,
+    prop: &ExtActionProposition,
+    id: Uint128,
+) -> StdResult<ProposalStatus> {
+    let total_votes = prop.total_votes;
+    if is_vetoed(config, prop) {
+        return Ok(ProposalStatus::Rejected);
+    } else if is_passed(config, &prop.tally, prop.confirmed_signers, total_votes) {
+        return Ok(ProposalStatus::Passed);
+    }
+
+    let voters = VOTE_RECORD.add_suffix(&id.to_be_bytes()).get_len(storage)?;
+    let members = VOTE_SNAPSHOTS
+        .add_suffix(&id.to_be_bytes())
+        .get_len(storage)?;
+
+    let mut best_tally = prop.tally.clone();
+    best_tally.yes += total_votes.saturating_sub(prop.tally.total());
+    let best_signers = prop.confirmed_signers + members.saturating_sub(voters);
+
+    if is_passed(config, &best_tally, best_signers, total_votes) {
+        Ok(ProposalStatus::Open)
+    } else {
+        Ok(ProposalStatus::Rejected)
+    }
+}
+
+/// Returns StdResult<()>
+///
+/// moves a proposal to a new status, keeping track of the pending ones
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to the contract's storage
+/// * `id`      - a reference to the proposal's ID
+/// * `prop`    - a mutable reference to the proposal
+/// * `status`  - the proposal's new status
+fn set_status(
+    storage: &mut dyn Storage,
+    id: &Uint128,
+    prop: &mut ExtActionProposition,
+    status: ProposalStatus,
+) -> StdResult<()> {
+    if prop.status.is_pending() != status.is_pending() {
+        track_pending_prop(storage, id, prop.proposer.as_str(), status.is_pending())?;
+    }
+    prop.status = status;
+    Ok(())
+}
+
+/// Returns StdResult<()>
+///
+/// adds or removes a pending proposal from the global and per proposer sets
+///
+/// # Arguments
+///
+/// * `storage`  - a mutable reference to the contract's storage
+/// * `id`       - a reference to the proposal's ID
+/// * `proposer` - a reference to the proposer's address
+/// * `pending`  - true if the proposal became pending, false if it closed
+fn track_pending_prop(
+    storage: &mut dyn Storage,
+    id: &Uint128,
+    proposer: &str,
+    pending: bool,
+) -> StdResult<()> {
+    let by_proposer = PENDING_PROPS_BY_PROPOSER.add_suffix(proposer.as_bytes());
+    if pending {
+        PENDING_PROPS.insert(storage, id, &true)?;
+        by_proposer.insert(storage, id, &true)?;
+    } else {
+        PENDING_PROPS.remove(storage, id)?;
+        by_proposer.remove(storage, id)?;
+    }
+    Ok(())
+}
+
+/// Returns StdResult<u32> of the number of proposals expired
+///
+/// expires the given proposals whose time limit ran out
+///
+/// # Arguments
+///
+/// * `storage` - a mutable reference to the contract's storage
+/// * `config`  - a reference to the contract's config
+/// * `env`     - a reference to the Env of contract's environment
+/// * `ids`     - IDs of the proposals to check
+fn expire_props(
+    storage: &mut dyn Storage,
+    config: &Config,
+    env: &Env,
+    ids: Vec<Uint128>,
+) -> StdResult<u32> {
+    let mut expired = 0_u32;
+    for id in ids {
+        if let Some(mut prop) = PROPOSALS.get(storage, &id) {
+            if prop.status.is_pending() && is_expired(config, &prop, env) {
+                set_status(storage, &id, &mut prop, ProposalStatus::Expired)?;
+                PROPOSALS.insert(storage, &id, &prop)?;
+                expired += 1;
+            }
+        }
+    }
+    Ok(expired)
+}
+
+/// Returns Result<(), ContractError>
+///
+/// checks that a proposer can have one more pending proposal, expiring those whose time ran out
+/// first since they still count until expired
+///
+/// # Arguments
+///
+/// * `storage`  - a mutable reference to the contract's storage
+/// * `config`   - a reference to the contract's config
+/// * `env`      - a reference to the Env of contract's environment
+/// * `proposer` - a reference to the proposer's address
+fn check_pending_caps(
+    storage: &mut dyn Storage,
+    config: &Config,
+    env: &Env,
+    proposer: &str,
+) -> Result<(), ContractError> {
+    let limits = &config.proposal_limits;
+    if let Some(max) = limits.max_open_per_proposer {
+        let pending = PENDING_PROPS_BY_PROPOSER.add_suffix(proposer.as_bytes());
+        if pending.get_len(storage)? >= max {
+            let ids = pending.iter_keys(storage)?.collect::<StdResult<Vec<_>>>()?;
+            expire_props(storage, config, env, ids)?;
+            if pending.get_len(storage)? >= max {
+                return Err(ContractError::ProposerLimitReached { max });
+            }
+        }
+    }
+    if let Some(max) = limits.max_open {
+        if PENDING_PROPS.get_len(storage)? >= max {
+            let ids = PENDING_PROPS
+                .iter_keys(storage)?
+                .collect::<StdResult<Vec<_>>>()?;
+            expire_props(storage, config, env, ids)?;
+            if PENDING_PROPS.get_len(storage)? >= max {
+                return Err(ContractError::PendingLimitReached { max });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns bool result of checking whether a proposal's time limit ran out
+///
+/// # Arguments
+///
+/// * `config` - a reference to the contract's config
+/// * `prop`   - a reference to the proposal being checked
+/// * `env`    - a reference to the Env of contract's environment
+fn is_expired(config: &Config, prop: &ExtActionProposition, env: &Env) -> bool {
+    // passed props get the execution delay on top, so one passing late can still be executed
+    let time_limit = match prop.status {
+        ProposalStatus::Passed => config.prop_time_limit + config.execution_delay,
+        _ => config.prop_time_limit,
+    };
+    prop.proposed_at.plus_seconds(time_limit) <= env.block.time
+}
+
+/// Returns bool result of validating an address' viewing key
+///
+/// # Arguments
+///
+/// * `storage`     - a reference to the contract's storage
+/// * `account`     - a reference to the str whose key should be validated
+/// * `viewing_key` - String key used for authentication
+fn is_key_valid(storage: &dyn Storage, account: &str, viewing_key: String) -> bool {
+    ViewingKey::check(storage, account, &viewing_key).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use cosmwasm_std::OwnedDeps;
+
+    use crate::state::{ProposalLimits, StakingConfig, VestingSchedule};
+
+    type MockDeps = OwnedDeps<
+        cosmwasm_std::testing::MockStorage,
+        cosmwasm_std::testing::MockApi,
+        cosmwasm_std::testing::MockQuerier,
+    >;
+
+    fn init(roles: Option<Vec<RoleAssignment>>, max_execution_attempts: Option<u32>) -> MockDeps {
+        let mut msg = init_msg();
+        msg.roles = roles;
+        msg.max_execution_attempts = max_execution_attempts;
+        init_with(msg)
+    }
+
+    fn init_with(msg: InstantiateMsg) -> MockDeps {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        deps
+    }
+
+    fn init_msg() -> InstantiateMsg {
+        InstantiateMsg {
+            time_limit: 1000,
+            stakeholders: vec![
+                StakeAssignment {
+                    holder: "alice".to_string(),
+                    stake: Uint128::from(60_u128),
+                    vesting: None,
+                },
+                StakeAssignment {
+                    holder: "bob".to_string(),
+                    stake: Uint128::from(40_u128),
+                    vesting: None,
+                },
+            ],
+            threshold: Threshold::Percentage {
+                percent: Decimal::percent(50),
+            },
+            quorum: None,
+            veto_threshold: None,
+            roles: None,
+            restrict_execution: false,
+            joining_roles: None,
+            lock_votes: false,
+            execution_delay: None,
+            max_execution_attempts: None,
+            proposal_limits: None,
+            staking: None,
+        }
+    }
+
+    fn propose(deps: &mut MockDeps, proposer: &str) -> Result<Response, ContractError> {
+        propose_at(deps, proposer, mock_env())
+    }
+
+    fn propose_at(
+        deps: &mut MockDeps,
+        proposer: &str,
+        env: Env,
+    ) -> Result<Response, ContractError> {
+        let msg = ExecuteMsg::ProposeAction {
+            title: "Pay carol".to_string(),
+            description: String::new(),
+            links: None,
+            prop_msgs: vec![CosmosMsg::Bank(BankMsg::Send {
+                to_address: "carol".to_string(),
+                amount: vec![Coin::new(100, "uscrt")],
+            })],
+        };
+        execute(deps.as_mut(), env, mock_info(proposer, &[]), msg)
+    }
+
+    fn vote(deps: &mut MockDeps, voter: &str, vote: Vote) -> Result<Response, ContractError> {
+        vote_on(deps, voter, 1, vote)
+    }
+
+    fn vote_on(
+        deps: &mut MockDeps,
+        voter: &str,
+        id: u128,
+        vote: Vote,
+    ) -> Result<Response, ContractError> {
+        let msg = ExecuteMsg::VoteAction {
+            action_prop: Uint128::from(id),
+            vote,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(voter, &[]), msg)
+    }
+
+    fn execute_prop(deps: &mut MockDeps) -> Response {
+        let msg = ExecuteMsg::Execute {
+            proposal_id: Uint128::from(1_u128),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap()
+    }
+
+    fn reply_with(deps: &mut MockDeps, result: SubMsgResult) {
+        let msg = Reply {
+            id: EXECUTE_PROP_REPLY_ID,
+            result,
+        };
+        reply(deps.as_mut(), mock_env(), msg).unwrap();
+    }
+
+    fn failure() -> SubMsgResult {
+        SubMsgResult::Err("insufficient funds".to_string())
+    }
+
+    fn prop(deps: &MockDeps) -> ExtActionProposition {
+        PROPOSALS
+            .get(&deps.storage, &Uint128::from(1_u128))
+            .unwrap()
+    }
+
+    fn passed_prop(max_execution_attempts: Option<u32>) -> MockDeps {
+        let mut deps = init(None, max_execution_attempts);
+        propose(&mut deps, "alice").unwrap();
+        vote(&mut deps, "alice", Vote::Yes).unwrap();
+        assert_eq!(prop(&deps).status, ProposalStatus::Passed);
+        deps
+    }
+
+    #[test]
+    fn reply_success_marks_prop_executed() {
+        let mut deps = passed_prop(None);
+        let res = execute_prop(&mut deps);
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].id, EXECUTE_PROP_REPLY_ID);
+
+        let msg = ExecuteMsg::RunProposal {
+            proposal_id: Uint128::from(1_u128),
+        };
+        let contract = mock_env().contract.address;
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(contract.as_str(), &[]),
+            msg,
+        )
+        .unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].id, PROP_MSG_REPLY_ID);
+
+        let data = Binary::from(b"paid".to_vec());
+        let res = reply(
+            deps.as_mut(),
+            mock_env(),
+            Reply {
+                id: PROP_MSG_REPLY_ID,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: Some(data.clone()),
+                }),
+            },
+        )
+        .unwrap();
+        assert_eq!(res.data, Some(data.clone()));
+
+        reply_with(
+            &mut deps,
+            SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(data.clone()),
+            }),
+        );
+        let prop = prop(&deps);
+        assert_eq!(prop.status, ProposalStatus::Executed);
+        assert_eq!(prop.execution_attempts, 1);
+        assert_eq!(
+            prop.execution_result,
+            Some(ExecutionResult::Success { data: Some(data) })
+        );
+        assert!(EXECUTING_PROP.may_load(&deps.storage).unwrap().is_none());
+    }
+
+    #[test]
+    fn reply_failure_marks_prop_failed() {
+        let mut deps = passed_prop(None);
+        execute_prop(&mut deps);
+
+        reply_with(&mut deps, failure());
+        let prop = prop(&deps);
+        assert_eq!(prop.status, ProposalStatus::ExecutionFailed);
+        assert_eq!(
+            prop.execution_result,
+            Some(ExecutionResult::Failure {
+                error: "insufficient funds".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn execute_from_contract_is_unauthorized() {
+        let mut deps = passed_prop(None);
+        let msg = ExecuteMsg::Execute {
+            proposal_id: Uint128::from(1_u128),
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(mock_env().contract.address.as_str(), &[]),
+            msg,
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+    }
+
+    #[test]
+    fn voter_without_proposer_role_cannot_propose() {
+        let roles = vec![RoleAssignment {
+            address: "alice".to_string(),
+            roles: vec![Role::Voter],
+        }];
+        let mut deps = init(Some(roles), None);
+
+        let err = propose(&mut deps, "alice").unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::MissingRole {
+                role: Role::Proposer
+            }
+        ));
+        propose(&mut deps, "bob").unwrap();
+    }
+
+    #[test]
+    fn observer_cannot_vote() {
+        let roles = vec![RoleAssignment {
+            address: "bob".to_string(),
+            roles: vec![Role::Observer],
+        }];
+        let mut deps = init(Some(roles), None);
+        propose(&mut deps, "alice").unwrap();
+
+        let err = vote(&mut deps, "bob", Vote::Yes).unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::MissingRole { role: Role::Voter }
+        ));
+        vote(&mut deps, "alice", Vote::Yes).unwrap();
+    }
+
+    #[test]
+    fn transfer_recipient_gets_joining_roles() {
+        let mut deps = init(None, None);
+        let msg = ExecuteMsg::TransferVotes {
+            recipient: "carol".to_string(),
+            num_votes: Uint128::from(10_u128),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap();
+        assert_eq!(roles_of(&deps.storage, "carol"), Role::all());
+
+        propose(&mut deps, "carol").unwrap();
+        vote(&mut deps, "carol", Vote::Yes).unwrap();
+    }
+
+    #[test]
+    fn observer_cannot_vote_through_a_delegate() {
+        let roles = vec![RoleAssignment {
+            address: "bob".to_string(),
+            roles: vec![Role::Observer],
+        }];
+        let mut deps = init(Some(roles), None);
+        let msg = ExecuteMsg::DelegateVotes {
+            delegate: "alice".to_string(),
+        };
+        let err = execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), msg).unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::MissingRole { role: Role::Voter }
+        ));
+    }
+
+    #[test]
+    fn delegate_skips_delegators_who_lost_the_voter_role() {
+        let mut deps = init(None, None);
+        let msg = ExecuteMsg::DelegateVotes {
+            delegate: "alice".to_string(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), msg).unwrap();
+        ROLES
+            .insert(&mut deps.storage, &"bob".to_string(), &vec![Role::Observer])
+            .unwrap();
+        propose(&mut deps, "alice").unwrap();
+
+        vote(&mut deps, "alice", Vote::Yes).unwrap();
+        assert_eq!(prop(&deps).tally.yes, Uint128::from(60_u128));
+    }
+
+    fn staking_msg(unbonding_period: u64, execution_delay: Option<u64>) -> InstantiateMsg {
+        InstantiateMsg {
+            time_limit: 1000,
+            stakeholders: vec![],
+            threshold: Threshold::Percentage {
+                percent: Decimal::percent(50),
+            },
+            quorum: None,
+            veto_threshold: None,
+            roles: None,
+            restrict_execution: false,
+            joining_roles: None,
+            lock_votes: false,
+            execution_delay,
+            max_execution_attempts: None,
+            proposal_limits: None,
+            staking: Some(StakingConfig {
+                token: ContractInfo {
+                    code_hash: "hash".to_string(),
+                    address: "token".to_string(),
+                },
+                unbonding_period,
+                min_stake: Uint128::from(10_u128),
+            }),
+        }
+    }
+
+    fn stake_tokens(
+        deps: &mut MockDeps,
+        token: &str,
+        staker: &str,
+        amount: u128,
+    ) -> Result<Response, ContractError> {
+        let msg = ExecuteMsg::Receive {
+            sender: Addr::unchecked(staker),
+            from: Addr::unchecked(staker),
+            amount: Uint128::from(amount),
+            memo: None,
+            msg: Some(to_binary(&ReceiveMsg::Stake {})?),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(token, &[]), msg)
+    }
+
+    #[test]
+    fn unbonding_must_outlast_execution_delay() {
+        let mut deps = mock_dependencies();
+        let msg = staking_msg(1200, Some(500));
+        let err =
+            instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::InvalidUnbondingPeriod { min: 1500, .. }
+        ));
+    }
+
+    #[test]
+    fn stake_unstake_and_claim() {
+        let mut deps = mock_dependencies();
+        let msg = staking_msg(2000, None);
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        assert!(stake_tokens(&mut deps, "other", "dave", 50).is_err());
+        assert!(stake_tokens(&mut deps, "token", "dave", 5).is_err());
+        stake_tokens(&mut deps, "token", "dave", 50).unwrap();
+        assert_eq!(
+            TOT_VOTES.load(&deps.storage).unwrap(),
+            Uint128::from(50_u128)
+        );
+
+        let unstake = ExecuteMsg::Unstake {
+            amount: Uint128::from(45_u128),
+        };
+        assert!(execute(deps.as_mut(), mock_env(), mock_info("dave", &[]), unstake).is_err());
+        let unstake = ExecuteMsg::Unstake {
+            amount: Uint128::from(20_u128),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("dave", &[]), unstake).unwrap();
+        assert_eq!(
+            STAKEHOLDERS.get(&deps.storage, &"dave".to_string()),
+            Some(Uint128::from(30_u128))
+        );
+        assert_eq!(
+            TOT_VOTES.load(&deps.storage).unwrap(),
+            Uint128::from(30_u128)
+        );
+
+        let claim = ExecuteMsg::ClaimUnbonded {};
+        assert!(execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("dave", &[]),
+            claim.clone()
+        )
+        .is_err());
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(2000);
+        let res = execute(deps.as_mut(), env, mock_info("dave", &[]), claim).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert!(UNBONDING.get(&deps.storage, &"dave".to_string()).is_none());
+    }
+
+    fn delegate(deps: &mut MockDeps, delegator: &str, delegate: &str) {
+        let msg = ExecuteMsg::DelegateVotes {
+            delegate: delegate.to_string(),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(delegator, &[]), msg).unwrap();
+    }
+
+    fn ballot(deps: &MockDeps, owner: &str) -> Option<Ballot> {
+        VOTE_RECORD
+            .add_suffix(&1_u128.to_be_bytes())
+            .get(&deps.storage, &owner.to_string())
+    }
+
+    #[test]
+    fn delegate_votes_for_new_delegators_without_changing_own_ballot() {
+        let mut deps = init(None, None);
+        propose(&mut deps, "alice").unwrap();
+        vote(&mut deps, "alice", Vote::No).unwrap();
+        assert!(vote(&mut deps, "alice", Vote::No).is_err());
+
+        delegate(&mut deps, "bob", "alice");
+        vote(&mut deps, "alice", Vote::No).unwrap();
+        assert_eq!(prop(&deps).tally.no, Uint128::from(100_u128));
+        assert_eq!(
+            ballot(&deps, "bob").unwrap().cast_by,
+            Addr::unchecked("alice")
+        );
+    }
+
+    #[test]
+    fn stakeholder_overrides_delegate_ballot() {
+        let mut deps = init(None, None);
+        delegate(&mut deps, "bob", "alice");
+        propose(&mut deps, "alice").unwrap();
+        vote(&mut deps, "alice", Vote::Abstain).unwrap();
+        assert_eq!(prop(&deps).tally.abstain, Uint128::from(100_u128));
+
+        vote(&mut deps, "bob", Vote::No).unwrap();
+        vote(&mut deps, "alice", Vote::Yes).unwrap();
+        let tally = prop(&deps).tally;
+        assert_eq!(tally.yes, Uint128::from(60_u128));
+        assert_eq!(tally.no, Uint128::from(40_u128));
+        assert_eq!(
+            ballot(&deps, "bob").unwrap().cast_by,
+            Addr::unchecked("bob")
+        );
+    }
+
+    fn init_limits(limits: ProposalLimits) -> MockDeps {
+        let mut msg = init_msg();
+        msg.proposal_limits = Some(limits);
+        init_with(msg)
+    }
+
+    fn later(seconds: u64) -> Env {
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(seconds);
+        env
+    }
+
+    #[test]
+    fn proposing_requires_min_votes() {
+        let mut deps = init_limits(ProposalLimits {
+            min_votes: Uint128::from(50_u128),
+            ..ProposalLimits::default()
+        });
+
+        let err = propose(&mut deps, "bob").unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::InsufficientVotesToPropose { .. }
+        ));
+        propose(&mut deps, "alice").unwrap();
+    }
+
+    #[test]
+    fn proposer_cap_frees_up_once_props_expire() {
+        let mut deps = init_limits(ProposalLimits {
+            max_open_per_proposer: Some(1),
+            ..ProposalLimits::default()
+        });
+        propose(&mut deps, "alice").unwrap();
+
+        let err = propose(&mut deps, "alice").unwrap_err();
+        assert!(matches!(
+            err,
+            ContractError::ProposerLimitReached { max: 1 }
+        ));
+        propose(&mut deps, "bob").unwrap();
+        propose_at(&mut deps, "alice", later(1000)).unwrap();
+        assert_eq!(prop(&deps).status, ProposalStatus::Expired);
+    }
+
+    #[test]
+    fn pending_cap_applies_to_props_passing_again() {
+        let mut deps = init_limits(ProposalLimits {
+            max_open: Some(1),
+            ..ProposalLimits::default()
+        });
+        propose(&mut deps, "alice").unwrap();
+        let err = propose(&mut deps, "bob").unwrap_err();
+        assert!(matches!(err, ContractError::PendingLimitReached { max: 1 }));
+
+        vote(&mut deps, "alice", Vote::No).unwrap();
+        assert_eq!(prop(&deps).status, ProposalStatus::Rejected);
+        propose(&mut deps, "bob").unwrap();
+
+        let err = vote(&mut deps, "alice", Vote::Yes).unwrap_err();
+        assert!(matches!(err, ContractError::PendingLimitReached { max: 1 }));
+    }
+
+    #[test]
+    fn purge_only_checks_pending_props() {
+        let mut deps = init(None, None);
+        propose(&mut deps, "alice").unwrap();
+        vote(&mut deps, "alice", Vote::No).unwrap();
+        propose(&mut deps, "alice").unwrap();
+        propose(&mut deps, "bob").unwrap();
+        assert_eq!(PENDING_PROPS.get_len(&deps.storage).unwrap(), 2);
+
+        let msg = ExecuteMsg::PurgeExpiredActions {
+            start_page: None,
+            page_size: None,
+        };
+        let res = execute(deps.as_mut(), later(1000), mock_info("bob", &[]), msg).unwrap();
+        assert_eq!(res.attributes[0].value, "2");
+        assert_eq!(PENDING_PROPS.get_len(&deps.storage).unwrap(), 0);
+        assert_eq!(prop(&deps).status, ProposalStatus::Rejected);
+    }
+
+    fn deposit(deps: &mut MockDeps, token: &str) -> Result<Response, ContractError> {
+        let msg = ExecuteMsg::Receive {
+            sender: Addr::unchecked("dave"),
+            from: Addr::unchecked("dave"),
+            amount: Uint128::from(100_u128),
+            memo: Some("dues".to_string()),
+            msg: None,
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(token, &[]), msg)
+    }
+
+    #[test]
+    fn deposits_are_only_accepted_from_registered_tokens() {
+        let mut deps = init(None, None);
+        assert!(deposit(&mut deps, "token").is_err());
+
+        let msg = ExecuteMsg::RegisterToken {
+            token: ContractInfo {
+                code_hash: "hash".to_string(),
+                address: "token".to_string(),
+            },
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        let contract = mock_env().contract.address;
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(contract.as_str(), &[]),
+            msg,
+        )
+        .unwrap();
+        assert_eq!(res.messages.len(), 2);
+
+        deposit(&mut deps, "token").unwrap();
+        let deposit = DEPOSITS.get(&deps.storage, &Uint128::from(1_u128)).unwrap();
+        assert_eq!(deposit.from, Addr::unchecked("dave"));
+        assert_eq!(deposit.memo, Some("dues".to_string()));
+    }
+
+    #[test]
+    fn tally_keeps_prop_open_while_it_can_pass() {
+        let mut deps = init(None, None);
+        propose(&mut deps, "alice").unwrap();
+
+        vote(&mut deps, "bob", Vote::No).unwrap();
+        assert_eq!(prop(&deps).status, ProposalStatus::Open);
+    }
+
+    #[test]
+    fn tally_rejects_prop_that_cannot_pass() {
+        let mut deps = init(None, None);
+        propose(&mut deps, "alice").unwrap();
+
+        vote(&mut deps, "alice", Vote::No).unwrap();
+        assert_eq!(prop(&deps).status, ProposalStatus::Rejected);
+    }
+
+    #[test]
+    fn tally_rejects_vetoed_prop() {
+        let mut deps = init(None, None);
+        propose(&mut deps, "alice").unwrap();
+
+        vote(&mut deps, "bob", Vote::Veto).unwrap();
+        assert_eq!(prop(&deps).status, ProposalStatus::Rejected);
+        assert!(vote(&mut deps, "alice", Vote::Yes).is_err());
+    }
+
+    #[test]
+    fn quorum_keeps_prop_open_until_enough_vote() {
+        let mut msg = init_msg();
+        msg.quorum = Some(Decimal::percent(70));
+        let mut deps = init_with(msg);
+        propose(&mut deps, "alice").unwrap();
+
+        vote(&mut deps, "alice", Vote::Yes).unwrap();
+        assert_eq!(prop(&deps).status, ProposalStatus::Open);
+        vote(&mut deps, "bob", Vote::Abstain).unwrap();
+        assert_eq!(prop(&deps).status, ProposalStatus::Passed);
+    }
+
+    fn transfer(
+        deps: &mut MockDeps,
+        from: &str,
+        to: &str,
+        votes: u128,
+    ) -> Result<Response, ContractError> {
+        let msg = ExecuteMsg::TransferVotes {
+            recipient: to.to_string(),
+            num_votes: Uint128::from(votes),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info(from, &[]), msg)
+    }
+
+    #[test]
+    fn transferred_votes_cannot_vote_twice() {
+        let mut deps = init(None, None);
+        propose(&mut deps, "alice").unwrap();
+        vote(&mut deps, "bob", Vote::No).unwrap();
+
+        transfer(&mut deps, "bob", "carol", 40).unwrap();
+        assert!(vote(&mut deps, "carol", Vote::No).is_err());
+        transfer(&mut deps, "carol", "alice", 40).unwrap();
+        vote(&mut deps, "alice", Vote::No).unwrap();
+        let tally = prop(&deps).tally;
+        assert_eq!(tally.no, Uint128::from(100_u128));
+        assert_eq!(prop(&deps).total_votes, Uint128::from(100_u128));
+    }
+
+    fn votes_of(deps: &MockDeps, holder: &str) -> Option<Uint128> {
+        STAKEHOLDERS.get(&deps.storage, &holder.to_string())
+    }
+
+    #[test]
+    fn transfer_credits_recipients() {
+        let mut deps = init(None, None);
+        let msg = ExecuteMsg::TransferVotesBatch {
+            transfers: vec![
+                Transferer {
+                    addr: "carol".to_string(),
+                    amount: Uint128::from(10_u128),
+                },
+                Transferer {
+                    addr: "bob".to_string(),
+                    amount: Uint128::from(5_u128),
+                },
+            ],
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap();
+        assert_eq!(res.events.len(), 2);
+
+        assert_eq!(votes_of(&deps, "alice"), Some(Uint128::from(45_u128)));
+        assert_eq!(votes_of(&deps, "bob"), Some(Uint128::from(45_u128)));
+        assert_eq!(votes_of(&deps, "carol"), Some(Uint128::from(10_u128)));
+        assert_eq!(
+            TOT_VOTES.load(&deps.storage).unwrap(),
+            Uint128::from(100_u128)
+        );
+
+        transfer(&mut deps, "carol", "alice", 10).unwrap();
+        assert_eq!(votes_of(&deps, "carol"), None);
+    }
+
+    #[test]
+    fn transfer_rejects_self_and_empty_transfers() {
+        let mut deps = init(None, None);
+        assert!(transfer(&mut deps, "alice", "alice", 10).is_err());
+        assert!(transfer(&mut deps, "alice", "bob", 61).is_err());
+
+        let msg = ExecuteMsg::TransferVotesBatch { transfers: vec![] };
+        assert!(execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).is_err());
+        assert_eq!(votes_of(&deps, "alice"), Some(Uint128::from(60_u128)));
+    }
+
+    #[test]
+    fn only_vested_votes_count() {
+        let now = mock_env().block.time.seconds();
+        let mut msg = init_msg();
+        msg.stakeholders[1].vesting = Some(VestingSchedule {
+            amount: Uint128::from(40_u128),
+            start: now,
+            cliff: now,
+            end: now + 1000,
+        });
+        let mut deps = init_with(msg);
+
+        propose(&mut deps, "alice").unwrap();
+        assert_eq!(prop(&deps).total_votes, Uint128::from(60_u128));
+        assert!(vote(&mut deps, "bob", Vote::Yes).is_err());
+        assert!(transfer(&mut deps, "bob", "carol", 1).is_err());
+
+        propose_at(&mut deps, "alice", later(500)).unwrap();
+        let second = PROPOSALS
+            .get(&deps.storage, &Uint128::from(2_u128))
+            .unwrap();
+        assert_eq!(second.total_votes, Uint128::from(80_u128));
+        vote_on(&mut deps, "bob", 2, Vote::No).unwrap();
+        let second = PROPOSALS
+            .get(&deps.storage, &Uint128::from(2_u128))
+            .unwrap();
+        assert_eq!(second.tally.no, Uint128::from(20_u128));
+    }
+
+    fn init_err(msg: InstantiateMsg) -> ContractError {
+        let mut deps = mock_dependencies();
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap_err()
+    }
+
+    #[test]
+    fn instantiate_rejects_invalid_time_limits() {
+        let mut msg = init_msg();
+        msg.time_limit = 0;
+        assert!(matches!(
+            init_err(msg),
+            ContractError::InvalidTimeLimit {
+                min: MIN_TIME_LIMIT,
+                max: MAX_TIME_LIMIT
+            }
+        ));
+        let mut msg = init_msg();
+        msg.time_limit = MAX_TIME_LIMIT + 1;
+        assert!(matches!(
+            init_err(msg),
+            ContractError::InvalidTimeLimit { .. }
+        ));
+    }
+
+    #[test]
+    fn instantiate_rejects_invalid_stakeholders() {
+        let mut msg = init_msg();
+        msg.stakeholders[1].holder = "Bob".to_string();
+        assert!(matches!(
+            init_err(msg),
+            ContractError::InvalidStakeholder { holder } if holder == "Bob"
+        ));
+
+        let mut msg = init_msg();
+        msg.stakeholders[1].stake = Uint128::zero();
+        assert!(matches!(
+            init_err(msg),
+            ContractError::ZeroStake { holder } if holder == "bob"
+        ));
+
+        let mut msg = init_msg();
+        msg.stakeholders[1].holder = "alice".to_string();
+        assert!(matches!(
+            init_err(msg),
+            ContractError::DuplicateStakeholder { holder } if holder == "alice"
+        ));
+
+        let mut msg = init_msg();
+        msg.stakeholders.clear();
+        assert!(matches!(init_err(msg), ContractError::NoVotes {}));
+    }
+
+    fn transfer_from(
+        deps: &mut MockDeps,
+        env: Env,
+        votes: u128,
+    ) -> Result<Response, ContractError> {
+        let msg = ExecuteMsg::TransferVotesFrom {
+            owner: "alice".to_string(),
+            recipient: "carol".to_string(),
+            num_votes: Uint128::from(votes),
+        };
+        execute(deps.as_mut(), env, mock_info("payroll", &[]), msg)
+    }
+
+    #[test]
+    fn vote_allowances_are_spent_and_expire() {
+        let mut deps = init(None, None);
+        let expiration = mock_env().block.time.seconds() + 100;
+        let msg = ExecuteMsg::IncreaseVoteAllowance {
+            spender: "payroll".to_string(),
+            amount: Uint128::from(20_u128),
+            expiration: Some(expiration),
+        };
+        execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), msg).unwrap();
+
+        transfer_from(&mut deps, mock_env(), 15).unwrap();
+        assert_eq!(votes_of(&deps, "carol"), Some(Uint128::from(15_u128)));
+        assert!(matches!(
+            transfer_from(&mut deps, mock_env(), 10).unwrap_err(),
+            ContractError::InsufficientAllowance { .. }
+        ));
+        assert!(transfer_from(&mut deps, later(100), 5).is_err());
+        transfer_from(&mut deps, later(99), 5).unwrap();
+        assert_eq!(votes_of(&deps, "alice"), Some(Uint128::from(40_u128)));
+    }
+
+    #[test]
+    fn reply_failure_allows_retries_until_attempts_run_out() {
+        let mut deps = passed_prop(Some(2));
+
+        execute_prop(&mut deps);
+        reply_with(&mut deps, failure());
+        let first = prop(&deps);
+        assert_eq!(first.status, ProposalStatus::Passed);
+        assert_eq!(first.execution_attempts, 1);
+
+        execute_prop(&mut deps);
+        reply_with(&mut deps, failure());
+        let second = prop(&deps);
+        assert_eq!(second.status, ProposalStatus::ExecutionFailed);
+        assert_eq!(second.execution_attempts, 2);
+    }
+
+    #[test]
+    fn failed_actions_can_be_retried_until_they_expire() {
+        let mut deps = passed_prop(Some(3));
+        execute_prop(&mut deps);
+        reply_with(&mut deps, failure());
+        assert_eq!(prop(&deps).status, ProposalStatus::Passed);
+
+        let msg = ExecuteMsg::Execute {
+            proposal_id: Uint128::from(1_u128),
+        };
+        let res = execute(deps.as_mut(), later(1000), mock_info("alice", &[]), msg).unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(prop(&deps).status, ProposalStatus::Expired);
+    }
+
+    fn as_contract(deps: &mut MockDeps, msg: ExecuteMsg) -> Result<Response, ContractError> {
+        let contract = mock_env().contract.address;
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(contract.as_str(), &[]),
+            msg,
+        )
+    }
+
+    fn pay_carol() -> CosmosMsg {
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "carol".to_string(),
+            amount: vec![Coin::new(100, "uscrt")],
+        })
+    }
+
+    #[test]
+    fn schedules_run_when_due_for_their_number_of_runs() {
+        let mut deps = init(None, None);
+        let start = mock_env().block.time.seconds() + 10;
+        let msg = ExecuteMsg::CreateSchedule {
+            msg: pay_carol(),
+            start,
+            interval: 100,
+            runs: 2,
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("alice", &[]),
+            msg.clone(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        as_contract(&mut deps, msg).unwrap();
+
+        let run = |deps: &mut MockDeps, env: Env| {
+            let msg = ExecuteMsg::ExecuteSchedule {
+                id: Uint128::from(1_u128),
+            };
+            execute(deps.as_mut(), env, mock_info("bob", &[]), msg)
+        };
+        assert!(matches!(
+            run(&mut deps, mock_env()).unwrap_err(),
+            ContractError::ScheduleNotDue { next_run } if next_run == start
+        ));
+        let res = run(&mut deps, later(10)).unwrap();
+        assert_eq!(res.messages[0].msg, pay_carol());
+        assert!(matches!(
+            run(&mut deps, later(109)).unwrap_err(),
+            ContractError::ScheduleNotDue { .. }
+        ));
+        run(&mut deps, later(110)).unwrap();
+        assert!(SCHEDULES
+            .get(&deps.storage, &Uint128::from(1_u128))
+            .is_none());
+        assert!(run(&mut deps, later(210)).is_err());
+    }
+
+    #[test]
+    fn schedules_cannot_start_in_the_past() {
+        let mut deps = init(None, None);
+        let msg = ExecuteMsg::CreateSchedule {
+            msg: pay_carol(),
+            start: mock_env().block.time.seconds() - 1,
+            interval: 100,
+            runs: 2,
+        };
+        assert!(matches!(
+            as_contract(&mut deps, msg).unwrap_err(),
+            ContractError::InvalidSchedule { .. }
+        ));
+    }
+
+    fn spend_at(deps: &mut MockDeps, env: Env, amount: u128) -> Result<Response, ContractError> {
+        let msg = ExecuteMsg::Spend {
+            asset: "uscrt".to_string(),
+            recipient: "carol".to_string(),
+            amount: Uint128::from(amount),
+        };
+        execute(deps.as_mut(), env, mock_info("bob", &[]), msg)
+    }
+
+    fn allow_bob(deps: &mut MockDeps) {
+        let msg = ExecuteMsg::SetSpendAllowance {
+            member: "bob".to_string(),
+            asset: Asset::Native {
+                denom: "uscrt".to_string(),
+            },
+            limit: Uint128::from(500_u128),
+            window: 100,
+        };
+        as_contract(deps, msg).unwrap();
+    }
+
+    #[test]
+    fn spending_is_capped_over_a_rolling_window() {
+        let mut deps = init(None, None);
+        allow_bob(&mut deps);
+
+        spend_at(&mut deps, mock_env(), 300).unwrap();
+        spend_at(&mut deps, later(50), 200).unwrap();
+        assert!(matches!(
+            spend_at(&mut deps, later(99), 1).unwrap_err(),
+            ContractError::SpendLimitExceeded { .. }
+        ));
+        spend_at(&mut deps, later(100), 300).unwrap();
+        assert!(spend_at(&mut deps, later(100), 1).is_err());
+        spend_at(&mut deps, later(150), 200).unwrap();
+    }
+
+    #[test]
+    fn removed_members_lose_their_spending_allowances() {
+        let mut deps = init(None, None);
+        allow_bob(&mut deps);
+
+        let msg = ExecuteMsg::UpdateMembers {
+            add: vec![],
+            remove: vec!["bob".to_string()],
+        };
+        as_contract(&mut deps, msg).unwrap();
+        assert!(spend_at(&mut deps, mock_env(), 1).is_err());
+    }
 }
@@ -1,6 +1,8 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{StdError, Timestamp, Uint128};
 use thiserror::Error;
 
+use crate::state::Role;
+
 #[derive(Error, Debug)]
 pub enum ContractError {
     #[error("{0}")]
@@ -15,6 +17,97 @@ pub enum ContractError {
     #[error("This address is unauthorized and/or viewing key is not valid")]
     ViewingKeyOrUnauthorized {},
 
+    #[error("Invalid threshold: {reason}")]
+    InvalidThreshold { reason: String },
+
+    #[error("Quorum must be at most 1")]
+    InvalidQuorum {},
+
+    #[error("Veto threshold must be greater than 0 and at most 1")]
+    InvalidVetoThreshold {},
+
+    #[error("{field} must be between {min} and {max} characters long")]
+    InvalidLength {
+        field: String,
+        min: usize,
+        max: usize,
+    },
+
+    #[error("A propostion can have at most {max} links")]
+    TooManyLinks { max: usize },
+
+    #[error("{holder} is not a valid stakeholder address")]
+    InvalidStakeholder { holder: String },
+
+    #[error("{holder} is listed as a stakeholder more than once")]
+    DuplicateStakeholder { holder: String },
+
+    #[error("{holder} cannot be given a stake of zero")]
+    ZeroStake { holder: String },
+
+    #[error("The stakeholders must hold at least one vote between them")]
+    NoVotes {},
+
+    #[error("Time limit must be between {min} and {max} seconds")]
+    InvalidTimeLimit { min: u64, max: u64 },
+
+    #[error("Execution delay can be at most {max} seconds")]
+    InvalidExecutionDelay { max: u64 },
+
+    #[error("Unbonding period must be between {min} and {max} seconds")]
+    InvalidUnbondingPeriod { min: u64, max: u64 },
+
+    #[error("Votes can only be gained by staking while the contract has a staking token")]
+    StakedVotesOnly {},
+
+    #[error("Stakes must be 0 or at least {min}")]
+    BelowMinimumStake { min: Uint128 },
+
+    #[error("This contract does not have a staking token")]
+    NoStakingToken {},
+
+    #[error("Props must be executable at least once")]
+    InvalidExecutionAttempts {},
+
+    #[error("Invalid schedule: {reason}")]
+    InvalidSchedule { reason: String },
+
+    #[error("This schedule's next run is not due until {next_run}")]
+    ScheduleNotDue { next_run: u64 },
+
+    #[error("Invalid vesting schedule: {reason}")]
+    InvalidVestingSchedule { reason: String },
+
+    #[error("Insufficient allowance: allowance={allowance}, required={required}")]
+    InsufficientAllowance {
+        allowance: Uint128,
+        required: Uint128,
+    },
+
+    #[error("This propostion cannot be executed until {executable_after}")]
+    Timelocked { executable_after: Timestamp },
+
+    #[error("Spending limit exceeded: available={available}, required={required}")]
+    SpendLimitExceeded {
+        available: Uint128,
+        required: Uint128,
+    },
+
+    #[error("At most {max} payments can be made from an allowance in one window")]
+    TooManySpends { max: usize },
+
+    #[error("This requires the {role} role")]
+    MissingRole { role: Role },
+
+    #[error("Proposing requires at least {min} vested votes")]
+    InsufficientVotesToPropose { min: Uint128 },
+
+    #[error("A stakeholder can have at most {max} pending propositions")]
+    ProposerLimitReached { max: u32 },
+
+    #[error("The contract can have at most {max} pending propositions")]
+    PendingLimitReached { max: u32 },
+
     #[error("Submessage (id: {id:?}) reply cannot be parsed.")]
     ParseReplyError { id: u64 },
 
@@ -1,9 +1,12 @@
-use cosmwasm_std::{Addr, Binary, CosmosMsg, Uint128};
+use cosmwasm_std::{Addr, Binary, CosmosMsg, Decimal, Uint128};
 use schemars::JsonSchema;
 use secret_toolkit::{permit::Permit, serialization::Json, utils::HandleCallback};
 use serde::{Deserialize, Serialize};
 
-use crate::state::ExtActionProposition;
+use crate::state::{
+    Asset, Config, Deposit, ExtActionProposition, ProposalLimits, ProposalStatus, Role, Schedule,
+    SpendAllowance, StakingConfig, Threshold, Transferer, Unbonding, VestingSchedule, Vote,
+};
 
 pub const BLOCK_SIZE: usize = 256;
 
@@ -11,6 +14,17 @@ pub const BLOCK_SIZE: usize = 256;
 pub struct InstantiateMsg {
     pub time_limit: u64, // time before props expire, in seconds
     pub stakeholders: Vec<StakeAssignment>,
+    pub threshold: Threshold,            // votes required for a prop to pass
+    pub quorum: Option<Decimal>,         // share of votes that must take part for a prop to pass
+    pub veto_threshold: Option<Decimal>, // share of votes whose vetoes reject a prop for good, 1/3 if None
+    pub roles: Option<Vec<RoleAssignment>>, // overrides the default of every role for stakeholders
+    pub restrict_execution: bool,        // whether only executors can execute passed props
+    pub joining_roles: Option<Vec<Role>>, // roles of new stakeholders made by transfers or staking, every role if None
+    pub lock_votes: bool,                 // whether ballots are final once cast
+    pub execution_delay: Option<u64>, // seconds between a prop passing and its execution, 0 if None
+    pub max_execution_attempts: Option<u32>, // times a passed prop can be executed, 1 if None
+    pub proposal_limits: Option<ProposalLimits>, // limits on proposing, unlimited if None
+    pub staking: Option<StakingConfig>, // token staked for votes, stakeholders must be empty if set
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -20,11 +34,118 @@ pub enum ExecuteMsg {
         recipient: String,
         num_votes: Uint128,
     },
+    TransferVotesBatch {
+        transfers: Vec<Transferer>,
+    },
+    /// lets `spender` transfer up to `amount` more of the sender's votes
+    IncreaseVoteAllowance {
+        spender: String,
+        amount: Uint128,
+        expiration: Option<u64>,
+    },
+    /// lowers the votes `spender` can transfer on the sender's behalf
+    DecreaseVoteAllowance {
+        spender: String,
+        amount: Uint128,
+        expiration: Option<u64>,
+    },
+    /// transfers votes on behalf of `owner`, using the allowance they gave the sender
+    TransferVotesFrom {
+        owner: String,
+        recipient: String,
+        num_votes: Uint128,
+    },
+    /// SNIP-20 hook called by a token after tokens are sent to this contract, tokens sent without
+    /// a message are deposited into the treasury
+    Receive {
+        sender: Addr,
+        from: Addr,
+        amount: Uint128,
+        memo: Option<String>,
+        msg: Option<Binary>,
+    },
+    /// gives up votes for the staking token, which can be claimed after the unbonding period
+    Unstake {
+        amount: Uint128,
+    },
+    /// sends the sender the unstaked tokens whose unbonding period is over
+    ClaimUnbonded {},
     ProposeAction {
-        prop_msg: CosmosMsg,
+        title: String,
+        description: String,
+        links: Option<Vec<String>>,
+        prop_msgs: Vec<CosmosMsg>,
     },
     VoteAction {
         action_prop: Uint128,
+        vote: Vote,
+    },
+    RetractVote {
+        action_prop: Uint128,
+    },
+    DelegateVotes {
+        delegate: String,
+    },
+    Undelegate {},
+    Execute {
+        proposal_id: Uint128,
+    },
+    /// dispatches a prop's messages, only callable by this contract while executing the prop
+    RunProposal {
+        proposal_id: Uint128,
+    },
+    /// sets the votes of the stakeholders in `add` and removes those in `remove`, only callable
+    /// by this contract through a prop
+    UpdateMembers {
+        add: Vec<StakeAssignment>,
+        remove: Vec<String>,
+    },
+    /// replaces the roles of each address, an empty list removes them all, and optionally the
+    /// roles of those who become stakeholders by receiving votes or staking, only callable by
+    /// this contract through a prop
+    SetRoles {
+        assignments: Vec<RoleAssignment>,
+        joining_roles: Option<Vec<Role>>,
+    },
+    /// sets up `msg` to be dispatched `runs` times, every `interval` seconds from `start`, only
+    /// callable by this contract through a prop
+    CreateSchedule {
+        msg: CosmosMsg,
+        start: u64,
+        interval: u64,
+        runs: u32,
+    },
+    /// stops a schedule, only callable by this contract through a prop
+    CancelSchedule {
+        id: Uint128,
+    },
+    /// dispatches a schedule's message once its next run is due, callable by anyone
+    ExecuteSchedule {
+        id: Uint128,
+    },
+    /// lets `member` send up to `limit` of an asset every `window` seconds without a prop, a
+    /// limit of 0 removes the allowance, only callable by this contract through a prop
+    SetSpendAllowance {
+        member: String,
+        asset: Asset,
+        limit: Uint128,
+        window: u64,
+    },
+    /// sends funds within the sender's spending allowance for the denom or token address `asset`
+    Spend {
+        asset: String,
+        recipient: String,
+        amount: Uint128,
+    },
+    /// registers the treasury with a SNIP-20 token so it accepts and can view deposits of it, only
+    /// callable by this contract through a prop
+    RegisterToken {
+        token: ContractInfo,
+    },
+    /// withdraws a prop that has not been executed, callable by its proposer or, through a
+    /// prop of its own, by this contract
+    CancelProposal {
+        id: Uint128,
     },
     PurgeExpiredActions {
         start_page: Option<u32>,
@@ -41,13 +162,47 @@ pub enum ExecuteMsg {
     },
 }
 
+/// messages sent along with tokens through the SNIP-20 `Send` hook
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    /// stakes the tokens for votes
+    Stake {},
+}
+
+/// SNIP-20 messages this contract sends to tokens
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Snip20Msg {
+    RegisterReceive {
+        code_hash: String,
+        padding: Option<String>,
+    },
+    Transfer {
+        recipient: String,
+        amount: Uint128,
+        memo: Option<String>,
+        padding: Option<String>,
+    },
+    SetViewingKey {
+        key: String,
+        padding: Option<String>,
+    },
+}
+
+impl HandleCallback for Snip20Msg {
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
     QueryEx {},
+    Config {},
     AllActions {
         start_page: Option<u32>,
         page_size: Option<u32>,
+        status: Option<ProposalStatus>,
         viewer: String,
         key: String,
     },
@@ -56,14 +211,47 @@ pub enum QueryMsg {
         viewer: String,
         key: String,
     },
-    AllCompletedActions {
+    FailedActions {
         start_page: Option<u32>,
         page_size: Option<u32>,
         viewer: String,
         key: String,
     },
-    QueryCompletedAction {
-        id: Uint128,
+    Stakeholders {
+        start_page: Option<u32>,
+        page_size: Option<u32>,
+        viewer: String,
+        key: String,
+    },
+    Schedules {
+        start_page: Option<u32>,
+        page_size: Option<u32>,
+        viewer: String,
+        key: String,
+    },
+    Tokens {
+        viewer: String,
+        key: String,
+    },
+    Deposits {
+        start_page: Option<u32>,
+        page_size: Option<u32>,
+        viewer: String,
+        key: String,
+    },
+    Stakeholder {
+        address: String,
+        viewer: String,
+        key: String,
+    },
+    VoteAllowance {
+        owner: String,
+        spender: String,
+        viewer: String,
+        key: String,
+    },
+    SpendAllowances {
+        member: String,
         viewer: String,
         key: String,
     },
@@ -78,8 +266,14 @@ impl QueryMsg {
         match self {
             Self::AllActions { viewer, key, .. } => (viewer.to_string(), key.clone()),
             Self::QueryAction { viewer, key, .. } => (viewer.to_string(), key.clone()),
-            Self::AllCompletedActions { viewer, key, .. } => (viewer.to_string(), key.clone()),
-            Self::QueryCompletedAction { viewer, key, .. } => (viewer.to_string(), key.clone()),
+            Self::FailedActions { viewer, key, .. } => (viewer.to_string(), key.clone()),
+            Self::Stakeholders { viewer, key, .. } => (viewer.to_string(), key.clone()),
+            Self::Schedules { viewer, key, .. } => (viewer.to_string(), key.clone()),
+            Self::Tokens { viewer, key } => (viewer.to_string(), key.clone()),
+            Self::Deposits { viewer, key, .. } => (viewer.to_string(), key.clone()),
+            Self::Stakeholder { viewer, key, .. } => (viewer.to_string(), key.clone()),
+            Self::VoteAllowance { viewer, key, .. } => (viewer.to_string(), key.clone()),
+            Self::SpendAllowances { viewer, key, .. } => (viewer.to_string(), key.clone()),
             _ => panic!("This query type does not require authentication"),
         }
     }
@@ -92,16 +286,37 @@ pub enum QueryWithPermit {
     AllActions {
         start_page: Option<u32>,
         page_size: Option<u32>,
+        status: Option<ProposalStatus>,
     },
     QueryAction {
         id: Uint128,
     },
-    AllCompletedActions {
+    FailedActions {
         start_page: Option<u32>,
         page_size: Option<u32>,
     },
-    QueryCompletedAction {
-        id: Uint128,
+    Stakeholders {
+        start_page: Option<u32>,
+        page_size: Option<u32>,
+    },
+    Schedules {
+        start_page: Option<u32>,
+        page_size: Option<u32>,
+    },
+    Tokens {},
+    Deposits {
+        start_page: Option<u32>,
+        page_size: Option<u32>,
+    },
+    Stakeholder {
+        address: String,
+    },
+    VoteAllowance {
+        owner: String,
+        spender: String,
+    },
+    SpendAllowances {
+        member: String,
     },
 }
 
@@ -114,6 +329,44 @@ pub enum QueryAnswer {
     QueryAction {
         action: ExtActionProposition,
     },
+    Config {
+        config: Config,
+    },
+    Stakeholders {
+        stakeholders: Vec<StakeAssignment>,
+        count: u32,
+        total_votes: Uint128,
+    },
+    Schedules {
+        schedules: Vec<(Uint128, Schedule)>,
+        count: u32,
+    },
+    Tokens {
+        tokens: Vec<ContractInfo>,
+    },
+    Deposits {
+        deposits: Vec<(Uint128, Deposit)>,
+        count: u32,
+    },
+    Stakeholder {
+        address: String,
+        votes: Uint128,
+        vested_votes: Uint128,
+        unvested_votes: Uint128,
+        delegate: Option<Addr>,
+        roles: Vec<Role>,
+        unbonding: Vec<Unbonding>,
+    },
+    VoteAllowance {
+        owner: String,
+        spender: String,
+        amount: Uint128,
+        expiration: Option<u64>,
+    },
+    SpendAllowances {
+        member: String,
+        allowances: Vec<SpendAllowance>,
+    },
     ViewingKeyError {
         error: String,
     },
@@ -133,4 +386,13 @@ pub struct ContractInfo {
 pub struct StakeAssignment {
     pub holder: String,
     pub stake: Uint128,
+    /// releases part of the stake over time, only the released part can vote
+    pub vesting: Option<VestingSchedule>,
+}
+
+/// address and roles combo
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Clone, Debug)]
+pub struct RoleAssignment {
+    pub address: String,
+    pub roles: Vec<Role>,
 }
@@ -1,3 +1,5 @@
+use std::fmt;
+
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -6,7 +8,9 @@ use secret_toolkit::{
     storage::{Item, Keymap},
 };
 
-use cosmwasm_std::{Addr, CosmosMsg, Timestamp, Uint128};
+use cosmwasm_std::{Addr, Binary, CosmosMsg, Decimal, Timestamp, Uint128};
+
+use crate::msg::ContractInfo;
 
 /// Map of permission holders and number of votes
 pub static STAKEHOLDERS: Keymap<String, Uint128> = Keymap::new(b"stakeholders");
@@ -15,14 +19,58 @@ pub static TOT_VOTES: Item<Uint128> = Item::new(b"votes");
 /// Current prop number
 pub static TOT_PROPS: Item<Uint128> = Item::new(b"props");
 
-/// Map of pending actions to vote on
-pub static PENDING_ACTIONS: Keymap<Uint128, ExtActionProposition, Json> =
-    Keymap::new(b"actionprop");
-/// Map of pending stake adjustments
-pub static COMPLETED_ACTIONS: Keymap<Uint128, ExtActionProposition> = Keymap::new(b"stakeprop");
+/// Map of every action proposition, whatever its status
+pub static PROPOSALS: Keymap<Uint128, ExtActionProposition, Json> = Keymap::new(b"actionprop");
+
+// Votes each stakeholder held when a prop was made. Must be used with a suffix of the prop ID
+pub static VOTE_SNAPSHOTS: Keymap<String, Uint128> = Keymap::new(b"votesnapshot");
+
+// Record of the ballot cast with each stakeholder's votes. Must be used with a suffix of the prop ID
+pub static VOTE_RECORD: Keymap<String, Ballot> = Keymap::new(b"voterecord");
+
+/// Map of recurring messages set up by props
+pub static SCHEDULES: Keymap<Uint128, Schedule, Json> = Keymap::new(b"schedules");
+/// Current schedule number
+pub static TOT_SCHEDULES: Item<Uint128> = Item::new(b"totschedules");
+
+// Funds a member can send without a prop, keyed by denom or token address. Must be used with a
+// suffix of the member's address
+pub static SPEND_ALLOWANCES: Keymap<String, SpendAllowance> = Keymap::new(b"spendallowances");
+
+/// Map of token addresses to the SNIP-20 tokens registered with the treasury
+pub static TOKENS: Keymap<String, ContractInfo> = Keymap::new(b"tokens");
+/// Map of every SNIP-20 deposit into the treasury
+pub static DEPOSITS: Keymap<Uint128, Deposit> = Keymap::new(b"deposits");
+/// Current deposit number
+pub static TOT_DEPOSITS: Item<Uint128> = Item::new(b"totdeposits");
+
+/// Map of stakeholders to the schedule part of their votes is released on
+pub static VESTING: Keymap<String, VestingSchedule> = Keymap::new(b"vesting");
+
+// Votes a spender can transfer on a stakeholder's behalf. Must be used with a suffix of the
+// stakeholder's address
+pub static VOTE_ALLOWANCES: Keymap<String, VoteAllowance> = Keymap::new(b"voteallowances");
+
+/// Map of former stakers to the tokens they are waiting to get back
+pub static UNBONDING: Keymap<String, Vec<Unbonding>> = Keymap::new(b"unbonding");
+
+/// Map of addresses to the roles the group set for them
+pub static ROLES: Keymap<String, Vec<Role>> = Keymap::new(b"roles");
+/// Map of stakeholders to the roles they got by joining, used unless the group set their roles
+pub static MEMBER_ROLES: Keymap<String, Vec<Role>> = Keymap::new(b"memberroles");
 
-// Record of whether an address voted. Must be used with a suffix of the prop ID
-pub static VOTE_RECORD: Keymap<String, bool> = Keymap::new(b"stakeprop");
+/// Map of stakeholders to the address voting on their behalf
+pub static DELEGATIONS: Keymap<String, Addr> = Keymap::new(b"delegations");
+// Stakeholders delegating to an address. Must be used with a suffix of the delegate's address
+pub static DELEGATORS: Keymap<String, bool> = Keymap::new(b"delegators");
+
+/// Set of the IDs of props that are open or passed
+pub static PENDING_PROPS: Keymap<Uint128, bool> = Keymap::new(b"pendingprops");
+// Set of the IDs of a proposer's pending props. Must be used with a suffix of the proposer's address
+pub static PENDING_PROPS_BY_PROPOSER: Keymap<Uint128, bool> = Keymap::new(b"pendingpropsby");
+
+/// ID of the prop whose messages are being dispatched, read back by the reply handler
+pub static EXECUTING_PROP: Item<Uint128> = Item::new(b"executingprop");
 
 /// Basic configuration struct
 pub static CONFIG_KEY: Item<Config> = Item::new(b"config");
@@ -33,33 +81,401 @@ pub const PREFIX_REVOKED_PERMITS: &str = "revoked_permits";
 pub struct Config {
     pub contract_address: Addr,
     pub prop_time_limit: u64,
+    pub threshold: Threshold,
+    // share of the total votes that must take part for a prop to pass
+    pub quorum: Option<Decimal>,
+    // share of the total votes whose vetoes reject a prop for good
+    pub veto_threshold: Decimal,
+    // whether executing passed props requires the executor role, anyone can if false
+    pub restrict_execution: bool,
+    // roles of addresses that become stakeholders by receiving votes or staking
+    pub joining_roles: Vec<Role>,
+    // whether ballots are final once cast
+    pub lock_votes: bool,
+    // seconds between a prop passing and the earliest it can be executed
+    pub execution_delay: u64,
+    // times a passed prop can be executed before a failure is final
+    pub max_execution_attempts: u32,
+    // limits on who can propose and how many props can be pending
+    pub proposal_limits: ProposalLimits,
+    // token staked for votes, votes are fixed if None
+    pub staking: Option<StakingConfig>,
 }
 
+/// Token stakeholders stake to gain votes
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
-pub struct StakeProposition {
-    // Votes supporting the proposal
-    pub confirmed_votes: Uint128,
-    // Time proposition was made
-    pub proposed_at: Timestamp,
-    // Vote stake recipient
-    pub recipient: String,
-    // proposed vote amount
-    pub num_votes: Uint128,
+pub struct StakingConfig {
+    // SNIP-20 token being staked
+    pub token: ContractInfo,
+    // seconds between unstaking and being able to claim the tokens back
+    pub unbonding_period: u64,
+    // smallest stake a stakeholder can hold, which keeps the number of stakeholders every prop
+    // snapshots in check
+    pub min_stake: Uint128,
+}
+
+/// Limits that keep props from piling up
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq, Eq, JsonSchema)]
+pub struct ProposalLimits {
+    // vested votes a stakeholder needs to propose
+    pub min_votes: Uint128,
+    // open or passed props a single stakeholder can have, unlimited if None
+    pub max_open_per_proposer: Option<u32>,
+    // open or passed props the contract can have, unlimited if None
+    pub max_open: Option<u32>,
+}
+
+/// Rule a proposal's supporting votes are measured against
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Threshold {
+    /// passes once the supporting votes reach a fixed weight
+    AbsoluteWeight { weight: Uint128 },
+    /// passes once the supporting votes reach a share of the total votes, not counting abstentions
+    Percentage { percent: Decimal },
+    /// passes once a fixed number of stakeholders voted in favor, regardless of weight
+    Signers { count: u32 },
+}
+
+impl Threshold {
+    /// Returns true if the supporting votes satisfy this threshold
+    ///
+    /// # Arguments
+    ///
+    /// * `tally`       - a reference to the ballots cast on the proposal
+    /// * `signers`     - number of stakeholders supporting the proposal
+    /// * `total_votes` - total number of votes availible
+    pub fn is_met(&self, tally: &Tally, signers: u32, total_votes: Uint128) -> bool {
+        match self {
+            Threshold::AbsoluteWeight { weight } => tally.yes >= *weight,
+            Threshold::Percentage { percent } => {
+                let counted = total_votes.saturating_sub(tally.abstain);
+                !counted.is_zero() && Decimal::from_ratio(tally.yes, counted) >= *percent
+            }
+            Threshold::Signers { count } => signers >= *count,
+        }
+    }
+}
+
+/// Permissions an address can hold
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// can propose actions
+    Proposer,
+    /// can vote on actions, with their own votes and those delegated to them
+    Voter,
+    /// can execute passed actions when execution is restricted
+    Executor,
+    /// can read actions and stakeholders
+    Observer,
+}
+
+impl Role {
+    /// Returns every role, which stakeholders added by the group are given
+    pub fn all() -> Vec<Role> {
+        vec![Role::Proposer, Role::Voter, Role::Executor, Role::Observer]
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let role = match self {
+            Role::Proposer => "proposer",
+            Role::Voter => "voter",
+            Role::Executor => "executor",
+            Role::Observer => "observer",
+        };
+        write!(f, "{}", role)
+    }
+}
+
+/// Ballot options for a proposal
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Vote {
+    Yes,
+    No,
+    Abstain,
+    /// counts against the proposal like No, and rejects it for good once the veto threshold is met
+    Veto,
+}
+
+/// Ballot cast with a stakeholder's votes
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Ballot {
+    pub vote: Vote,
+    // the stakeholder themselves, or their delegate
+    pub cast_by: Addr,
+}
+
+/// Weight of the ballots cast on a proposal, per option
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Tally {
+    pub yes: Uint128,
+    pub no: Uint128,
+    pub abstain: Uint128,
+    pub veto: Uint128,
+}
+
+impl Tally {
+    /// Adds a ballot's weight to the matching option
+    pub fn add(&mut self, vote: Vote, weight: Uint128) {
+        match vote {
+            Vote::Yes => self.yes += weight,
+            Vote::No => self.no += weight,
+            Vote::Abstain => self.abstain += weight,
+            Vote::Veto => self.veto += weight,
+        }
+    }
+
+    /// Takes a previously added ballot's weight back out of the matching option
+    pub fn remove(&mut self, vote: Vote, weight: Uint128) {
+        match vote {
+            Vote::Yes => self.yes -= weight,
+            Vote::No => self.no -= weight,
+            Vote::Abstain => self.abstain -= weight,
+            Vote::Veto => self.veto -= weight,
+        }
+    }
+
+    /// Returns the weight of every ballot cast, whatever the option
+    pub fn total(&self) -> Uint128 {
+        self.yes + self.no + self.abstain + self.veto
+    }
+}
+
+/// Stage of a proposal's lifecycle
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProposalStatus {
+    /// accepting ballots
+    Open,
+    /// has the votes to pass and is waiting to be executed, ballots can still change
+    Passed,
+    /// can no longer gather the votes to pass unless ballots change
+    Rejected,
+    /// time limit ran out before it was executed
+    Expired,
+    /// messages were dispatched
+    Executed,
+    /// messages failed on every execution attempt it was allowed
+    ExecutionFailed,
+    /// withdrawn before it closed
+    Cancelled,
+}
+
+impl ProposalStatus {
+    /// Returns true while the proposal can still be executed
+    pub fn is_pending(&self) -> bool {
+        matches!(self, ProposalStatus::Open | ProposalStatus::Passed)
+    }
+}
+
+impl fmt::Display for ProposalStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = match self {
+            ProposalStatus::Open => "open",
+            ProposalStatus::Passed => "passed",
+            ProposalStatus::Rejected => "rejected",
+            ProposalStatus::Expired => "expired",
+            ProposalStatus::Executed => "executed",
+            ProposalStatus::ExecutionFailed => "execution_failed",
+            ProposalStatus::Cancelled => "cancelled",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+/// Outcome of dispatching a proposal's messages
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionResult {
+    Success { data: Option<Binary> },
+    Failure { error: String },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct ExtActionProposition {
-    // Votes supporting the proposal
-    pub confirmed_votes: Uint128,
+    // Short name of the proposal
+    pub title: String,
+    // What the proposal does and why
+    pub description: String,
+    // External references for reviewers
+    pub links: Vec<String>,
+    // Address that made the proposal
+    pub proposer: Addr,
+    // Stage of the proposal's lifecycle
+    pub status: ProposalStatus,
+    // Total number of votes availible when the proposal was made
+    pub total_votes: Uint128,
+    // Votes cast on the proposal
+    pub tally: Tally,
+    // Number of stakeholders supporting the proposal
+    pub confirmed_signers: u32,
     // Time proposition was made
     pub proposed_at: Timestamp,
-    pub cosmos_msg: CosmosMsg,
+    // Block height proposition was made at
+    pub proposed_at_height: u64,
+    // Earliest time the proposal can be executed, set when it passes
+    pub executable_after: Option<Timestamp>,
+    // Messages dispatched in order, all or none, when the proposal is executed
+    pub cosmos_msgs: Vec<CosmosMsg>,
+    // Number of times the proposal was executed
+    pub execution_attempts: u32,
+    // Outcome of the latest attempt at dispatching the messages
+    pub execution_result: Option<ExecutionResult>,
+}
+
+/// Cliff plus linear release of part of a stakeholder's votes, with times in seconds
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct VestingSchedule {
+    // votes released by the schedule, the rest of the stake is vested from the start
+    pub amount: Uint128,
+    // time the linear release is measured from
+    pub start: u64,
+    // time before which nothing is released
+    pub cliff: u64,
+    // time by which everything is released
+    pub end: u64,
+}
+
+impl VestingSchedule {
+    /// Returns the votes released by the given time
+    pub fn vested(&self, now: Timestamp) -> Uint128 {
+        let now = now.seconds();
+        if now < self.cliff {
+            Uint128::zero()
+        } else if now >= self.end {
+            self.amount
+        } else {
+            self.amount
+                .multiply_ratio(now - self.start, self.end - self.start)
+        }
+    }
+
+    /// Returns the votes still locked at the given time
+    pub fn unvested(&self, now: Timestamp) -> Uint128 {
+        self.amount - self.vested(now)
+    }
+}
+
+/// Votes a spender can transfer on a stakeholder's behalf
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq, Eq, JsonSchema)]
+pub struct VoteAllowance {
+    pub amount: Uint128,
+    // time in seconds after which the allowance can no longer be used, never if None
+    pub expiration: Option<u64>,
+}
+
+impl VoteAllowance {
+    /// Returns true once the allowance can no longer be used
+    pub fn is_expired(&self, now: Timestamp) -> bool {
+        match self.expiration {
+            Some(expiration) => now.seconds() >= expiration,
+            None => false,
+        }
+    }
+}
+
+/// Message dispatched at a fixed interval, such as a recurring payment
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Schedule {
+    pub msg: CosmosMsg,
+    // seconds between runs
+    pub interval: u64,
+    // time in seconds the next run is due
+    pub next_run: u64,
+    // runs left before the schedule is done
+    pub runs_left: u32,
+}
+
+/// Funds held by the contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Asset {
+    Native { denom: String },
+    Snip20 { token: ContractInfo },
+}
+
+impl Asset {
+    /// Returns the denom or token address the asset is known by
+    pub fn key(&self) -> &str {
+        match self {
+            Asset::Native { denom } => denom,
+            Asset::Snip20 { token } => &token.address,
+        }
+    }
+}
+
+/// Funds a member can send without a prop, within any window of time ending now
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct SpendAllowance {
+    pub asset: Asset,
+    // amount that can be sent in any window
+    pub limit: Uint128,
+    // length of the window in seconds
+    pub window: u64,
+    // payments made in the window ending at the latest payment
+    pub spends: Vec<Spend>,
+}
+
+impl SpendAllowance {
+    /// Returns the amount sent in the window ending at the given time
+    pub fn spent(&self, now: Timestamp) -> Uint128 {
+        let window_start = now.seconds().saturating_sub(self.window);
+        self.spends
+            .iter()
+            .filter(|spend| spend.at > window_start)
+            .fold(Uint128::zero(), |total, spend| total + spend.amount)
+    }
+
+    /// Returns the amount that can still be sent at the given time
+    pub fn available(&self, now: Timestamp) -> Uint128 {
+        self.limit.saturating_sub(self.spent(now))
+    }
+
+    /// Drops the payments that fell out of the window ending at the given time
+    pub fn prune(&mut self, now: Timestamp) {
+        let window_start = now.seconds().saturating_sub(self.window);
+        self.spends.retain(|spend| spend.at > window_start);
+    }
+}
+
+/// Payment made from a spending allowance
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Spend {
+    pub amount: Uint128,
+    // time in seconds the payment was made
+    pub at: u64,
+}
+
+/// SNIP-20 tokens received by the treasury
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Deposit {
+    // token that was received
+    pub token: Addr,
+    // address that called the token's Send
+    pub sender: Addr,
+    // owner of the tokens that were sent
+    pub from: Addr,
+    pub amount: Uint128,
+    pub memo: Option<String>,
+    pub received_at: Timestamp,
+}
+
+/// Unstaked tokens waiting to be claimed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct Unbonding {
+    pub amount: Uint128,
+    // time in seconds the tokens can be claimed from
+    pub release_at: u64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
 pub struct Transferer {
-    // holder address
+    // recipient address
     pub addr: String,
-    //votes being transfered from holder
+    // votes being transfered to recipient
     pub amount: Uint128,
 }